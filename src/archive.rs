@@ -0,0 +1,458 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter, types::Type};
+use serde::Serialize;
+
+use crate::dashboard::{GameSummary, ReportSummary};
+use crate::data::Data;
+use crate::pgn::Game;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS reports (
+        id             INTEGER PRIMARY KEY,
+        created_at     TEXT NOT NULL,
+        student_id     TEXT NOT NULL,
+        group_key      TEXT NOT NULL,
+        tournament_key TEXT NOT NULL,
+        data           TEXT NOT NULL,
+        white_game_id  TEXT NOT NULL,
+        black_game_id  TEXT NOT NULL,
+        white_pgn      TEXT NOT NULL,
+        black_pgn      TEXT NOT NULL,
+        white_points   TEXT,
+        black_points   TEXT,
+        white_moves    INTEGER NOT NULL,
+        black_moves    INTEGER NOT NULL,
+        wrong_color    INTEGER NOT NULL,
+        filename       TEXT NOT NULL,
+        xlsx           BLOB NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS reports_student_id ON reports (student_id);
+    CREATE INDEX IF NOT EXISTS reports_tournament_key ON reports (tournament_key, student_id);
+    CREATE INDEX IF NOT EXISTS reports_white_game_id ON reports (white_game_id);
+    CREATE INDEX IF NOT EXISTS reports_black_game_id ON reports (black_game_id);
+";
+
+/// Archive of the generated reports, stored in an SQLite database.
+///
+/// SQLite calls block, so they run on the blocking thread pool rather than in the async
+/// handlers.
+pub struct Archive {
+    connection: Arc<Mutex<Connection>>,
+}
+
+/// Generated report to be stored in the archive.
+pub struct NewReport {
+    pub created_at: DateTime<Local>,
+    pub data: Data,
+    pub white_game_id: String,
+    pub black_game_id: String,
+    pub game_white: Game,
+    pub game_black: Game,
+    pub filename: String,
+    pub xlsx: Vec<u8>,
+}
+
+/// Report stored in the archive with the summary of its games, without the file contents.
+pub struct StoredReport {
+    pub id: i64,
+    pub created_at: String,
+    pub data: Data,
+    pub white_game_id: String,
+    pub black_game_id: String,
+    pub summary: ReportSummary,
+}
+
+/// Page of the latest report of each student in each tournament, starting from the most recent
+/// one. Empty group and tournament match any report. The group and the tournament are compared
+/// by [`key`], so the case and the surrounding spaces do not matter.
+pub struct ReportQuery {
+    pub group: String,
+    pub tournament: String,
+
+    /// Only the reports with smaller IDs are returned, to continue from the previous page.
+    pub before: Option<i64>,
+    pub limit: u32,
+}
+
+/// Game submitted by the student with one of the colours.
+pub struct Claim {
+    pub game_id: String,
+    pub as_white: bool,
+    pub student_id: String,
+}
+
+/// File of the report stored in the archive.
+pub struct ReportFile {
+    pub student_id: String,
+    pub filename: String,
+    pub xlsx: Vec<u8>,
+}
+
+/// Report stored in the archive, without the file contents.
+#[derive(Serialize)]
+pub struct ArchivedReport {
+    pub id: i64,
+    pub created_at: String,
+    pub student_id: String,
+    pub white_game_id: String,
+    pub black_game_id: String,
+    pub filename: String,
+}
+
+impl Archive {
+    /// Opens the database, creating it if necessary. Returns `None` if the archive
+    /// is not configured.
+    pub fn open(path: Option<&Path>) -> anyhow::Result<Option<Self>> {
+        let Some(path) = path else {
+            return Ok(None);
+        };
+
+        let connection = Connection::open(path)
+            .and_then(|connection| connection.execute_batch(SCHEMA).map(|_| connection))
+            .with_context(|| format!("cannot open archive {}", path.display()))?;
+
+        Ok(Some(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        }))
+    }
+
+    /// Checks that the database can still be queried.
+    pub async fn check(&self) -> anyhow::Result<()> {
+        self.run(|connection| {
+            connection.query_row("SELECT 1", [], |_| Ok(()))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Stores the report and returns its ID.
+    pub async fn store(&self, report: NewReport) -> anyhow::Result<i64> {
+        let data = serde_json::to_string(&report.data)?;
+        let summary = ReportSummary::of(&report.game_white, &report.game_black);
+
+        self.run(move |connection| {
+            connection.execute(
+                "INSERT INTO reports (
+                    created_at, student_id, group_key, tournament_key, data, white_game_id,
+                    black_game_id, white_pgn, black_pgn, filename, xlsx, white_points,
+                    black_points, white_moves, black_moves, wrong_color
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                params![
+                    report.created_at.to_rfc3339(),
+                    report.data.student.id,
+                    key(&report.data.student.group),
+                    key(&report.data.subject.tournament),
+                    data,
+                    report.white_game_id,
+                    report.black_game_id,
+                    report.game_white.pgn,
+                    report.game_black.pgn,
+                    report.filename,
+                    report.xlsx,
+                    summary.white.points,
+                    summary.black.points,
+                    summary.white.moves,
+                    summary.black.moves,
+                    summary.wrong_color,
+                ],
+            )?;
+
+            Ok(connection.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Returns reports of the student, starting from the most recent one.
+    pub async fn list(&self, student_id: String) -> anyhow::Result<Vec<ArchivedReport>> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT id, created_at, student_id, white_game_id, black_game_id, filename
+                FROM reports WHERE student_id = ?1 ORDER BY id DESC",
+            )?;
+
+            let reports = statement
+                .query_map([student_id], |row| {
+                    Ok(ArchivedReport {
+                        id: row.get(0)?,
+                        created_at: row.get(1)?,
+                        student_id: row.get(2)?,
+                        white_game_id: row.get(3)?,
+                        black_game_id: row.get(4)?,
+                        filename: row.get(5)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+
+            Ok(reports)
+        })
+        .await
+    }
+
+    /// Returns a page of the latest reports of the students matching the query.
+    pub async fn reports(&self, query: ReportQuery) -> anyhow::Result<Vec<StoredReport>> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT id, created_at, data, white_game_id, black_game_id, white_points,
+                    black_points, white_moves, black_moves, wrong_color
+                FROM reports AS report
+                WHERE (?1 = '' OR group_key = ?1)
+                    AND (?2 = '' OR tournament_key = ?2)
+                    AND (?3 IS NULL OR id < ?3)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM reports AS newer
+                        WHERE newer.student_id = report.student_id
+                            AND newer.tournament_key = report.tournament_key
+                            AND newer.id > report.id
+                    )
+                ORDER BY id DESC
+                LIMIT ?4",
+            )?;
+
+            let params = params![
+                key(&query.group),
+                key(&query.tournament),
+                query.before,
+                query.limit,
+            ];
+            let reports = statement
+                .query_map(params, |row| {
+                    let data: String = row.get(2)?;
+                    let data = serde_json::from_str(&data).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e))
+                    })?;
+
+                    Ok(StoredReport {
+                        id: row.get(0)?,
+                        created_at: row.get(1)?,
+                        data,
+                        white_game_id: row.get(3)?,
+                        black_game_id: row.get(4)?,
+                        summary: ReportSummary {
+                            white: GameSummary {
+                                points: row.get(5)?,
+                                moves: row.get(7)?,
+                            },
+                            black: GameSummary {
+                                points: row.get(6)?,
+                                moves: row.get(8)?,
+                            },
+                            wrong_color: row.get(9)?,
+                        },
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+
+            Ok(reports)
+        })
+        .await
+    }
+
+    /// Returns all submissions of the games by any student.
+    pub async fn claims(&self, game_ids: Vec<String>) -> anyhow::Result<Vec<Claim>> {
+        if game_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.run(move |connection| {
+            let placeholders = vec!["?"; game_ids.len()].join(", ");
+            let mut statement = connection.prepare(&format!(
+                "SELECT white_game_id, 1, student_id FROM reports
+                WHERE white_game_id IN ({placeholders})
+                UNION
+                SELECT black_game_id, 0, student_id FROM reports
+                WHERE black_game_id IN ({placeholders})"
+            ))?;
+
+            let claims = statement
+                .query_map(params_from_iter(game_ids.iter().chain(&game_ids)), |row| {
+                    Ok(Claim {
+                        game_id: row.get(0)?,
+                        as_white: row.get(1)?,
+                        student_id: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+
+            Ok(claims)
+        })
+        .await
+    }
+
+    /// Returns file of the report, if the report exists.
+    pub async fn file(&self, id: i64) -> anyhow::Result<Option<ReportFile>> {
+        self.run(move |connection| {
+            let file = connection
+                .query_row(
+                    "SELECT student_id, filename, xlsx FROM reports WHERE id = ?1",
+                    [id],
+                    |row| {
+                        Ok(ReportFile {
+                            student_id: row.get(0)?,
+                            filename: row.get(1)?,
+                            xlsx: row.get(2)?,
+                        })
+                    },
+                )
+                .optional()?;
+
+            Ok(file)
+        })
+        .await
+    }
+
+    /// Runs the queries on the blocking thread pool.
+    async fn run<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || f(&connection.lock().unwrap())).await?
+    }
+}
+
+/// Returns the group or the tournament in the form they are compared in: trimmed and in lower
+/// case. Unlike `COLLATE NOCASE` in SQLite, the case is folded for Cyrillic letters too.
+fn key(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Language;
+    use crate::pgn;
+
+    const WHITE_PGN: &str = "[White \"student\"]\n[Black \"opponent\"]\n[Result \"1-0\"]\n\n\
+        1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    const BLACK_PGN: &str = "[White \"opponent\"]\n[Black \"someone\"]\n[Result \"1/2-1/2\"]\n\n\
+        1. e4 e5 1/2-1/2";
+
+    fn archive() -> Archive {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+
+        Archive {
+            connection: Arc::new(Mutex::new(connection)),
+        }
+    }
+
+    fn report(student_id: &str, group: &str, tournament: &str, games: (&str, &str)) -> NewReport {
+        let mut data = Data::default();
+        data.student.id = String::from(student_id);
+        data.student.group = String::from(group);
+        data.subject.tournament = String::from(tournament);
+
+        NewReport {
+            created_at: Local::now(),
+            data,
+            white_game_id: String::from(games.0),
+            black_game_id: String::from(games.1),
+            game_white: pgn::parse(WHITE_PGN, Language::default()).unwrap(),
+            game_black: pgn::parse(BLACK_PGN, Language::default()).unwrap(),
+            filename: String::from("report.xlsx"),
+            xlsx: vec![1, 2, 3],
+        }
+    }
+
+    fn query(group: &str, before: Option<i64>, limit: u32) -> ReportQuery {
+        ReportQuery {
+            group: String::from(group),
+            tournament: String::new(),
+            before,
+            limit,
+        }
+    }
+
+    async fn ids(archive: &Archive, query: ReportQuery) -> Vec<i64> {
+        let reports = archive.reports(query).await.unwrap();
+        reports.iter().map(|report| report.id).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_latest_reports() {
+        let archive = archive();
+        for report in [
+            report("111", "1234", "Блиц", ("a", "b")),
+            report("222", "1234", "Блиц", ("c", "d")),
+            // The same tournament typed in another case replaces the first report.
+            report("111", "1234", "блиц ", ("e", "f")),
+            report("111", "1234", "Рапид", ("g", "h")),
+            report("333", "4321", "Блиц", ("i", "j")),
+        ] {
+            archive.store(report).await.unwrap();
+        }
+
+        assert_eq!(ids(&archive, query("", None, 10)).await, [5, 4, 3, 2]);
+        assert_eq!(ids(&archive, query("", None, 2)).await, [5, 4]);
+        assert_eq!(ids(&archive, query("", Some(4), 2)).await, [3, 2]);
+        assert_eq!(ids(&archive, query(" 1234 ", None, 10)).await, [4, 3, 2]);
+
+        let tournament = ReportQuery {
+            tournament: String::from("БЛИЦ"),
+            ..query("", None, 10)
+        };
+        assert_eq!(ids(&archive, tournament).await, [5, 3, 2]);
+    }
+
+    #[tokio::test]
+    async fn finds_claims_of_games() {
+        let archive = archive();
+        archive
+            .store(report("111", "1234", "Blitz", ("a", "b")))
+            .await
+            .unwrap();
+        archive
+            .store(report("222", "1234", "Blitz", ("b", "a")))
+            .await
+            .unwrap();
+
+        let claims = archive.claims(vec![String::from("a")]).await.unwrap();
+        let mut claims: Vec<_> = claims
+            .iter()
+            .map(|claim| {
+                (
+                    claim.game_id.as_str(),
+                    claim.as_white,
+                    claim.student_id.as_str(),
+                )
+            })
+            .collect();
+        claims.sort();
+
+        assert_eq!(claims, [("a", false, "222"), ("a", true, "111")]);
+        assert!(archive.claims(Vec::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stores_summary_of_games() {
+        let archive = archive();
+        archive
+            .store(report("111", "1234", "Blitz", ("a", "b")))
+            .await
+            .unwrap();
+
+        let reports = archive.reports(query("", None, 10)).await.unwrap();
+        assert_eq!(reports[0].summary, expected_summary());
+    }
+
+    fn expected_summary() -> ReportSummary {
+        ReportSummary {
+            white: GameSummary {
+                points: Some(String::from("1")),
+                moves: 4,
+            },
+            black: GameSummary {
+                points: Some(String::from("½")),
+                moves: 1,
+            },
+            wrong_color: true,
+        }
+    }
+}
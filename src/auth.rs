@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::error::ApiError;
+use crate::i18n::Language;
+
+/// Name of the cookie with the session token.
+pub const SESSION_COOKIE: &str = "session";
+
+/// Sessions expire after this time since the login.
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(12 * 60 * 60);
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Can access the archived reports with their own student ID.
+    Student,
+
+    /// Can access all archived reports and the dashboard.
+    Teacher,
+}
+
+/// Account from the user store.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct User {
+    login: String,
+
+    /// Argon2 password hash in the PHC string format, e.g. `$argon2id$v=19$...`.
+    password_hash: String,
+    role: Role,
+
+    /// Student ID of the student's account.
+    #[serde(default)]
+    student_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UserStore {
+    #[serde(default)]
+    users: Vec<User>,
+}
+
+/// User of the current request, added to the request extensions by the auth middleware.
+#[derive(Clone, Serialize)]
+pub struct CurrentUser {
+    pub login: String,
+    pub role: Role,
+    pub student_id: Option<String>,
+}
+
+impl CurrentUser {
+    /// Returns whether the user can access reports of the student.
+    pub fn can_access_student(&self, student_id: &str) -> bool {
+        match self.role {
+            Role::Teacher => true,
+            Role::Student => self.student_id.as_deref() == Some(student_id),
+        }
+    }
+}
+
+struct Session {
+    user: CurrentUser,
+    expires_at: Instant,
+}
+
+/// Local user store and the sessions of the logged in users.
+pub struct Auth {
+    users: HashMap<String, User>,
+
+    /// Hash checked for unknown logins, so that the response time does not reveal which
+    /// accounts exist.
+    dummy_hash: String,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Auth {
+    /// Loads users from the TOML file. Without the file, there are no users and the protected
+    /// routes are inaccessible.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let users = match path {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("cannot read users from {}", path.display()))?;
+                let store: UserStore = toml::from_str(&content)
+                    .with_context(|| format!("invalid users in {}", path.display()))?;
+
+                for user in &store.users {
+                    PasswordHash::new(&user.password_hash).map_err(|e| {
+                        anyhow::anyhow!("invalid password hash of user {}: {e}", user.login)
+                    })?;
+                }
+
+                store.users
+            }
+            None => Vec::new(),
+        };
+
+        let salt = SaltString::encode_b64(b"lichess-xlsx-reports").map_err(anyhow::Error::msg)?;
+        let dummy_hash = Argon2::default()
+            .hash_password(b"", &salt)
+            .map_err(anyhow::Error::msg)?
+            .to_string();
+
+        Ok(Self {
+            users: users
+                .into_iter()
+                .map(|user| (user.login.clone(), user))
+                .collect(),
+            dummy_hash,
+            sessions: Mutex::default(),
+        })
+    }
+
+    /// Checks the credentials and starts a new session. Returns the session token.
+    ///
+    /// The password hash is checked on a blocking thread, since it takes tens of milliseconds
+    /// by design.
+    pub async fn login(self: Arc<Self>, login: String, password: String) -> Option<String> {
+        let auth = self.clone();
+        let user = tokio::task::spawn_blocking(move || auth.verify(&login, &password))
+            .await
+            .ok()??;
+
+        self.start_session(user)
+    }
+
+    /// Returns the user if the password is correct. A hash is checked even if the user does
+    /// not exist.
+    fn verify(&self, login: &str, password: &str) -> Option<CurrentUser> {
+        let user = self.users.get(login);
+        let hash = user.map_or(&self.dummy_hash, |user| &user.password_hash);
+        let hash = PasswordHash::new(hash).ok()?;
+
+        let verified = Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok();
+
+        user.filter(|_| verified).map(|user| CurrentUser {
+            login: user.login.clone(),
+            role: user.role,
+            student_id: user.student_id.clone(),
+        })
+    }
+
+    fn start_session(&self, user: CurrentUser) -> Option<String> {
+        let mut token = [0; 32];
+        getrandom::fill(&mut token).ok()?;
+        let token: String = token.iter().map(|byte| format!("{byte:02x}")).collect();
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(
+            token.clone(),
+            Session {
+                user,
+                expires_at: now + SESSION_LIFETIME,
+            },
+        );
+
+        Some(token)
+    }
+
+    pub fn logout(&self, token: &str) {
+        self.sessions.lock().unwrap().remove(token);
+    }
+
+    /// Returns the user of the session, if the session exists and has not expired.
+    pub fn user(&self, token: &str) -> Option<CurrentUser> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(token)?;
+
+        (session.expires_at > Instant::now()).then(|| session.user.clone())
+    }
+
+    fn authenticate(&self, request: &Request) -> Option<CurrentUser> {
+        let jar = CookieJar::from_headers(request.headers());
+        self.user(jar.get(SESSION_COOKIE)?.value())
+    }
+}
+
+/// Middleware allowing only logged in users of any role.
+pub async fn require_user(
+    State(auth): State<Arc<Auth>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, request, next, None).await
+}
+
+/// Middleware allowing only teachers.
+pub async fn require_teacher(
+    State(auth): State<Arc<Auth>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, request, next, Some(Role::Teacher)).await
+}
+
+async fn require_role(
+    auth: &Auth,
+    mut request: Request,
+    next: Next,
+    role: Option<Role>,
+) -> Result<Response, ApiError> {
+    let lang = Language::from_headers(request.headers());
+    let user = auth
+        .authenticate(&request)
+        .ok_or_else(|| ApiError::unauthorized(lang))?;
+
+    if role.is_some_and(|role| role != user.role) {
+        return Err(ApiError::forbidden(lang));
+    }
+
+    request.extensions_mut().insert(user);
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    /// Loads the users from a file with a name unique to the test.
+    fn auth(name: &str) -> Arc<Auth> {
+        let salt = SaltString::encode_b64(b"test-salt-value").unwrap();
+        let hash = Argon2::default()
+            .hash_password(b"secret", &salt)
+            .unwrap()
+            .to_string();
+
+        let path = env::temp_dir().join(format!("users-{name}-{}.toml", process::id()));
+        fs::write(
+            &path,
+            format!(
+                "[[users]]\nlogin = \"ivanov\"\npassword_hash = \"{hash}\"\nrole = \"student\"\n\
+                 student_id = \"123456\"\n"
+            ),
+        )
+        .unwrap();
+
+        let auth = Auth::load(Some(&path));
+        fs::remove_file(&path).unwrap();
+        Arc::new(auth.unwrap())
+    }
+
+    fn login(auth: &Arc<Auth>, login: &str, password: &str) -> Option<String> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(auth.clone().login(login.into(), password.into()))
+    }
+
+    #[test]
+    fn logs_in_with_correct_password() {
+        let auth = auth("login");
+        let token = login(&auth, "ivanov", "secret").unwrap();
+        let user = auth.user(&token).unwrap();
+
+        assert_eq!(user.role, Role::Student);
+        assert!(user.can_access_student("123456"));
+        assert!(!user.can_access_student("654321"));
+
+        auth.logout(&token);
+        assert!(auth.user(&token).is_none());
+    }
+
+    #[test]
+    fn rejects_wrong_credentials() {
+        let auth = auth("wrong-credentials");
+
+        assert!(login(&auth, "ivanov", "wrong").is_none());
+        assert!(login(&auth, "petrov", "secret").is_none());
+        assert!(login(&auth, "petrov", "").is_none());
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::{Config, ConfigArgs};
+use crate::data::{Data, UploadedGames};
+use crate::engine::Engine;
+use crate::error::ApiError;
+use crate::i18n::Language;
+use crate::lichess::Lichess;
+use crate::rules::RuleSets;
+use crate::validation::Formats;
+use crate::xlsx::{Report, spreadsheet_to_bytes};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Generates XLSX reports on chess games played on Lichess"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Starts the web server. This is the default command.
+    Serve,
+
+    /// Generates a report and writes it to disk without starting the server.
+    Generate(Box<GenerateArgs>),
+
+    /// Checks that the server running with the same configuration is alive. Used as the health
+    /// check of the container.
+    Healthcheck,
+}
+
+/// Report data is read from the file, if given, and then overridden by the flags.
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// JSON or TOML file with the report data in the same format as the API request.
+    #[arg(long)]
+    data: Option<PathBuf>,
+
+    /// Full name of the student.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Group number.
+    #[arg(long)]
+    group: Option<String>,
+
+    /// Student ID.
+    #[arg(long)]
+    id: Option<String>,
+
+    /// Full name of the teacher.
+    #[arg(long)]
+    teacher: Option<String>,
+
+    /// Name of the tournament.
+    #[arg(long)]
+    tournament: Option<String>,
+
+    /// Full name of the opponent.
+    #[arg(long)]
+    opponent: Option<String>,
+
+    /// Lichess URL or PGN file of the game played as White.
+    #[arg(long)]
+    white: Option<String>,
+
+    /// Lichess URL or PGN file of the game played as Black.
+    #[arg(long)]
+    black: Option<String>,
+
+    /// Language of the report: `ru` or `en`.
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Output file. Defaults to the report filename in the current directory.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Generates the report like the API does, but without registering the submitted games and
+/// storing the report in the archive.
+pub async fn generate(config: &Config, args: GenerateArgs) -> anyhow::Result<()> {
+    let lichess = Lichess::new(&config.lichess, Arc::default())?;
+    let rules = RuleSets::load(config.rules_path.as_deref())?;
+    let formats = Formats::load(config.formats_path.as_deref())?;
+
+    let mut data = match &args.data {
+        Some(path) => read_data(path)?,
+        None => Data::default(),
+    };
+
+    let overrides = [
+        (&args.name, &mut data.student.name),
+        (&args.group, &mut data.student.group),
+        (&args.id, &mut data.student.id),
+        (&args.teacher, &mut data.subject.teacher),
+        (&args.tournament, &mut data.subject.tournament),
+        (&args.opponent, &mut data.game.opponent),
+    ];
+    for (value, field) in overrides {
+        if let Some(value) = value {
+            field.clone_from(value);
+        }
+    }
+
+    if let Some(language) = &args.language {
+        data.options.language = Some(
+            Language::from_accept_language(language)
+                .ok_or_else(|| anyhow!("unknown language {language}"))?,
+        );
+    }
+
+    // Games given as files are compared with the games on Lichess and checked the same way as the
+    // downloaded ones. Their URLs are taken from the PGN, so that the validation still applies.
+    let uploads = UploadedGames {
+        white: game_source(args.white.as_deref(), &mut data.game.white_url)?,
+        black: game_source(args.black.as_deref(), &mut data.game.black_url)?,
+    };
+    let uploads = data.parse_uploads(&uploads).map_err(describe)?;
+
+    data.validate(&formats).map_err(describe)?;
+
+    let (mut game_white, mut game_black) = data
+        .load_games(&lichess, &rules, uploads)
+        .await
+        .map_err(|e| describe(e.into()))?;
+
+    if data.options.analysis {
+        let engine = Engine::from_config(&config.engine)
+            .ok_or_else(|| anyhow!("analysis requires a local engine, see ENGINE_PATH"))?;
+
+        tokio::try_join!(
+            engine.analyse(&mut game_white),
+            engine.analyse(&mut game_black)
+        )?;
+    }
+
+    let report = Report::new(data, game_white, game_black, config);
+    let bytes = spreadsheet_to_bytes(&report.generate_spreadsheet()?)?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(report.filename()));
+    fs::write(&output, bytes).with_context(|| format!("cannot write {}", output.display()))?;
+
+    println!("{}", output.display());
+
+    Ok(())
+}
+
+/// Requests `/healthz` of the server. The address it listens on on all interfaces is replaced
+/// with the loopback one.
+pub async fn healthcheck(config: &Config) -> anyhow::Result<()> {
+    let mut addr: SocketAddr = config
+        .listen_addr
+        .parse()
+        .with_context(|| format!("invalid listen address {}", config.listen_addr))?;
+
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr {
+            SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?
+        .get(format!("http://{addr}/healthz"))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+fn read_data(path: &Path) -> anyhow::Result<Data> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("cannot read data from {}", path.display()))?;
+
+    let data = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => toml::from_str(&content)?,
+        _ => serde_json::from_str(&content)?,
+    };
+
+    Ok(data)
+}
+
+/// Sets the game URL if the source is a URL. Otherwise reads the PGN file and returns its
+/// contents.
+fn game_source(source: Option<&str>, url: &mut String) -> anyhow::Result<Option<String>> {
+    let Some(source) = source else {
+        return Ok(None);
+    };
+
+    if source.starts_with("http://") || source.starts_with("https://") {
+        *url = String::from(source);
+        return Ok(None);
+    }
+
+    let pgn = fs::read_to_string(source).with_context(|| format!("cannot read {source}"))?;
+
+    Ok(Some(pgn))
+}
+
+/// Converts the API error into a message listing all invalid fields.
+fn describe(error: ApiError) -> anyhow::Error {
+    let mut message = error.message;
+
+    if error.errors.len() > 1 {
+        for field in &error.errors {
+            message.push_str(&format!("\n  {}: {}", field.field, field.message));
+        }
+    }
+
+    anyhow!(message)
+}
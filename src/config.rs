@@ -0,0 +1,507 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow, bail};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::name::NameFormat;
+use crate::xlsx::{FilenamePattern, Styles};
+
+/// Configuration of the server and the reports.
+///
+/// The values are layered: the defaults are overridden by the TOML file, then by the environment
+/// variables, and finally by the command line flags.
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen_addr: String,
+
+    /// Time after which a request is aborted with `408 Request Timeout`.
+    pub request_timeout_secs: u64,
+
+    pub rules_path: Option<PathBuf>,
+    pub submissions_path: Option<PathBuf>,
+    pub archive_path: Option<PathBuf>,
+    pub users_path: Option<PathBuf>,
+    pub formats_path: Option<PathBuf>,
+
+    pub engine: EngineConfig,
+    pub lichess: LichessConfig,
+    pub rate_limit: RateLimitConfig,
+    pub report: ReportConfig,
+    pub styles: Styles,
+    pub log: LogConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Path to the UCI engine. The analysis is unavailable without it.
+    pub path: Option<PathBuf>,
+
+    /// Maximum time of the analysis of a single game.
+    pub time_budget_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LichessConfig {
+    /// Time limit of downloading a game.
+    pub timeout_secs: u64,
+    pub user_agent: String,
+
+    /// Maximum number of games downloaded at the same time by all requests.
+    pub max_concurrent_requests: usize,
+}
+
+/// Limit of the report requests per client IP address.
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Sustained rate of the requests. `0` disables the limit.
+    pub requests_per_minute: u32,
+
+    /// Number of requests a client can make at once before being limited.
+    pub burst: u32,
+
+    /// Take the client address from the last entry of `X-Forwarded-For`. Enable only behind a
+    /// reverse proxy that appends the address to the header, since otherwise clients can forge it.
+    pub trust_forwarded_for: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    /// Title of the report replacing the one from the message catalog.
+    pub title: Option<String>,
+    pub filename_pattern: FilenamePattern,
+    pub name_format: NameFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+
+    /// Levels of the logged events in the `tracing` filter syntax, e.g. `info` or
+    /// `lichess_xlsx_reports=debug`.
+    pub filter: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+
+    /// One JSON object per line.
+    Json,
+}
+
+/// Command line flags overriding the configuration.
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// TOML configuration file.
+    #[arg(long, global = true, env = "CONFIG_PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Prints the resulting configuration and exits.
+    #[arg(long, global = true)]
+    pub print_config: bool,
+
+    /// Address the server listens on.
+    #[arg(long, global = true)]
+    pub listen_addr: Option<String>,
+
+    /// Maximum time of handling a request.
+    #[arg(long, global = true)]
+    pub request_timeout_secs: Option<u64>,
+
+    /// TOML file with the course rules.
+    #[arg(long, global = true)]
+    pub rules_path: Option<PathBuf>,
+
+    /// File with the index of the submitted games.
+    #[arg(long, global = true)]
+    pub submissions_path: Option<PathBuf>,
+
+    /// SQLite database with the report archive.
+    #[arg(long, global = true)]
+    pub archive_path: Option<PathBuf>,
+
+    /// TOML file with the users.
+    #[arg(long, global = true)]
+    pub users_path: Option<PathBuf>,
+
+    /// TOML file with the formats of the form fields.
+    #[arg(long, global = true)]
+    pub formats_path: Option<PathBuf>,
+
+    /// UCI engine used for the analysis.
+    #[arg(long, global = true)]
+    pub engine_path: Option<PathBuf>,
+
+    /// Maximum time of the analysis of a single game.
+    #[arg(long, global = true)]
+    pub engine_time_budget_ms: Option<u64>,
+
+    /// Time limit of downloading a game from Lichess.
+    #[arg(long, global = true)]
+    pub lichess_timeout_secs: Option<u64>,
+
+    /// Sustained rate of the report requests per client, `0` disables the limit.
+    #[arg(long, global = true)]
+    pub rate_limit_per_minute: Option<u32>,
+
+    /// Number of report requests a client can make at once before being limited.
+    #[arg(long, global = true)]
+    pub rate_limit_burst: Option<u32>,
+
+    /// Takes the client address from the `X-Forwarded-For` header set by the reverse proxy.
+    #[arg(long, global = true)]
+    pub rate_limit_trust_forwarded_for: Option<bool>,
+
+    /// Pattern of the report filename.
+    #[arg(long, global = true)]
+    pub filename_pattern: Option<FilenamePattern>,
+
+    /// Format of the player names in the game tables.
+    #[arg(long, global = true)]
+    pub name_format: Option<NameFormat>,
+
+    /// Format of the log output.
+    #[arg(long, global = true)]
+    pub log_format: Option<LogFormat>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addr: String::from("0.0.0.0:8000"),
+            request_timeout_secs: 15,
+            rules_path: None,
+            submissions_path: None,
+            archive_path: None,
+            users_path: None,
+            formats_path: None,
+            engine: EngineConfig::default(),
+            lichess: LichessConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            report: ReportConfig::default(),
+            styles: Styles::default(),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            time_budget_ms: 4000,
+        }
+    }
+}
+
+impl Default for LichessConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            user_agent: format!("lichess-xlsx-reports/{}", env!("CARGO_PKG_VERSION")),
+            max_concurrent_requests: 4,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 6,
+            burst: 3,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            filter: String::from("info"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            format => bail!("unknown log format {format}"),
+        }
+    }
+}
+
+impl Config {
+    /// Builds the configuration from all layers.
+    pub fn load(args: &ConfigArgs) -> anyhow::Result<Self> {
+        Self::load_with_env(args, |name| env::var_os(name))
+    }
+
+    /// Builds the configuration, reading the environment variables with `env`.
+    fn load_with_env(
+        args: &ConfigArgs,
+        env: impl Fn(&str) -> Option<OsString>,
+    ) -> anyhow::Result<Self> {
+        let mut config = match &args.config {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("cannot read config from {}", path.display()))?;
+
+                toml::from_str(&content)
+                    .with_context(|| format!("invalid config in {}", path.display()))?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_env(&env)?;
+        config.apply_args(args);
+
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, env: &impl Fn(&str) -> Option<OsString>) -> anyhow::Result<()> {
+        set_from_env(env, "LISTEN_ADDR", &mut self.listen_addr)?;
+        set_from_env(env, "REQUEST_TIMEOUT_SECS", &mut self.request_timeout_secs)?;
+        set_path_from_env(env, "RULES_PATH", &mut self.rules_path);
+        set_path_from_env(env, "SUBMISSIONS_PATH", &mut self.submissions_path);
+        set_path_from_env(env, "ARCHIVE_PATH", &mut self.archive_path);
+        set_path_from_env(env, "USERS_PATH", &mut self.users_path);
+        set_path_from_env(env, "FORMATS_PATH", &mut self.formats_path);
+        set_path_from_env(env, "ENGINE_PATH", &mut self.engine.path);
+        set_from_env(
+            env,
+            "ENGINE_TIME_BUDGET_MS",
+            &mut self.engine.time_budget_ms,
+        )?;
+        set_from_env(env, "LICHESS_TIMEOUT_SECS", &mut self.lichess.timeout_secs)?;
+        set_from_env(
+            env,
+            "LICHESS_MAX_CONCURRENT_REQUESTS",
+            &mut self.lichess.max_concurrent_requests,
+        )?;
+        set_from_env(
+            env,
+            "RATE_LIMIT_PER_MINUTE",
+            &mut self.rate_limit.requests_per_minute,
+        )?;
+        set_from_env(env, "RATE_LIMIT_BURST", &mut self.rate_limit.burst)?;
+        set_from_env(
+            env,
+            "RATE_LIMIT_TRUST_FORWARDED_FOR",
+            &mut self.rate_limit.trust_forwarded_for,
+        )?;
+        set_from_env(env, "FILENAME_PATTERN", &mut self.report.filename_pattern)?;
+        set_from_env(env, "NAME_FORMAT", &mut self.report.name_format)?;
+        set_from_env(env, "LOG_FORMAT", &mut self.log.format)?;
+        set_from_env(env, "RUST_LOG", &mut self.log.filter)?;
+
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &ConfigArgs) {
+        fn set<T: Clone>(value: &Option<T>, field: &mut T) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+
+        fn set_path(value: &Option<PathBuf>, field: &mut Option<PathBuf>) {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        }
+
+        set(&args.listen_addr, &mut self.listen_addr);
+        set(&args.request_timeout_secs, &mut self.request_timeout_secs);
+        set_path(&args.rules_path, &mut self.rules_path);
+        set_path(&args.submissions_path, &mut self.submissions_path);
+        set_path(&args.archive_path, &mut self.archive_path);
+        set_path(&args.users_path, &mut self.users_path);
+        set_path(&args.formats_path, &mut self.formats_path);
+        set_path(&args.engine_path, &mut self.engine.path);
+        set(&args.engine_time_budget_ms, &mut self.engine.time_budget_ms);
+        set(&args.lichess_timeout_secs, &mut self.lichess.timeout_secs);
+        set(
+            &args.rate_limit_per_minute,
+            &mut self.rate_limit.requests_per_minute,
+        );
+        set(&args.rate_limit_burst, &mut self.rate_limit.burst);
+        set(
+            &args.rate_limit_trust_forwarded_for,
+            &mut self.rate_limit.trust_forwarded_for,
+        );
+        set(&args.filename_pattern, &mut self.report.filename_pattern);
+        set(&args.name_format, &mut self.report.name_format);
+        set(&args.log_format, &mut self.log.format);
+    }
+}
+
+fn set_from_env<T>(
+    env: &impl Fn(&str) -> Option<OsString>,
+    name: &str,
+    field: &mut T,
+) -> anyhow::Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = env(name) {
+        *field = value
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid value of {name}: not valid UTF-8"))?
+            .parse()
+            .map_err(|e| anyhow!("invalid value of {name}: {e}"))?;
+    }
+
+    Ok(())
+}
+
+fn set_path_from_env(
+    env: &impl Fn(&str) -> Option<OsString>,
+    name: &str,
+    field: &mut Option<PathBuf>,
+) {
+    if let Some(value) = env(name) {
+        *field = Some(PathBuf::from(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::process;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        config: ConfigArgs,
+    }
+
+    /// Loads the configuration with the flags and the environment variables.
+    fn load(args: &[&str], vars: &[(&str, &str)]) -> anyhow::Result<Config> {
+        let cli = Cli::try_parse_from(["lichess-xlsx-reports"].iter().chain(args))?;
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+
+        Config::load_with_env(&cli.config, |name| vars.get(name).map(OsString::from))
+    }
+
+    /// Writes the configuration file with a name unique to the test.
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("config-{name}-{}.toml", process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn layers_defaults_file_env_and_flags() {
+        let path = config_file(
+            "layers",
+            "listen_addr = \"127.0.0.1:1000\"\n\
+            request_timeout_secs = 20\n\
+            [lichess]\n\
+            timeout_secs = 30\n\
+            [rate_limit]\n\
+            burst = 5\n",
+        );
+        let path = path.to_str().unwrap();
+
+        let file = load(&["--config", path], &[]).unwrap();
+        assert_eq!(file.listen_addr, "127.0.0.1:1000");
+        assert_eq!(file.request_timeout_secs, 20);
+        assert_eq!(file.lichess.timeout_secs, 30);
+        assert_eq!(file.rate_limit.burst, 5);
+        assert_eq!(file.rate_limit.requests_per_minute, 6);
+        assert_eq!(file.engine.time_budget_ms, 4000);
+
+        let vars = [
+            ("REQUEST_TIMEOUT_SECS", "25"),
+            ("LICHESS_TIMEOUT_SECS", "35"),
+            ("RATE_LIMIT_PER_MINUTE", "12"),
+        ];
+        let env = load(&["--config", path], &vars).unwrap();
+        assert_eq!(env.listen_addr, "127.0.0.1:1000");
+        assert_eq!(env.request_timeout_secs, 25);
+        assert_eq!(env.lichess.timeout_secs, 35);
+        assert_eq!(env.rate_limit.requests_per_minute, 12);
+
+        let args = [
+            "--config",
+            path,
+            "--request-timeout-secs",
+            "40",
+            "--lichess-timeout-secs",
+            "45",
+            "--rate-limit-per-minute",
+            "0",
+            "--rate-limit-burst",
+            "1",
+            "--rate-limit-trust-forwarded-for",
+            "true",
+        ];
+        let flags = load(&args, &vars).unwrap();
+        assert_eq!(flags.listen_addr, "127.0.0.1:1000");
+        assert_eq!(flags.request_timeout_secs, 40);
+        assert_eq!(flags.lichess.timeout_secs, 45);
+        assert_eq!(flags.rate_limit.requests_per_minute, 0);
+        assert_eq!(flags.rate_limit.burst, 1);
+        assert!(flags.rate_limit.trust_forwarded_for);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        for (name, content) in [
+            ("top-level", "listen_adr = \"127.0.0.1:1000\"\n"),
+            ("section", "[rate_limit]\nrequests_per_second = 1\n"),
+        ] {
+            let path = config_file(name, content);
+            let error = load(&["--config", path.to_str().unwrap()], &[])
+                .err()
+                .unwrap();
+
+            assert!(format!("{error:#}").contains("unknown field"), "{error:#}");
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_env_values() {
+        for (name, value) in [
+            ("REQUEST_TIMEOUT_SECS", "soon"),
+            ("RATE_LIMIT_TRUST_FORWARDED_FOR", "yes"),
+            ("LOG_FORMAT", "xml"),
+        ] {
+            let error = load(&[], &[(name, value)]).err().unwrap();
+            assert!(
+                error
+                    .to_string()
+                    .starts_with(&format!("invalid value of {name}")),
+                "{error}"
+            );
+        }
+    }
+}
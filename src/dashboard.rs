@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{Claim, ReportQuery, StoredReport};
+use crate::pgn::Game;
+use crate::result::GameResult;
+
+/// Games shorter than this number of full moves are flagged as short.
+const SHORT_GAME_MOVES: usize = 20;
+
+/// Number of submissions on a page of the dashboard, unless the request asks for another one.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Largest number of submissions on a page of the dashboard.
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Filter of the submissions shown on the dashboard. Empty values match any submission.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Filter {
+    pub group: String,
+    pub tournament: String,
+
+    /// ID of the last report of the previous page, see [`SubmissionPage::next`].
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+impl Filter {
+    /// Returns the page size, limited to [`MAX_PAGE_SIZE`].
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn into_query(self) -> ReportQuery {
+        ReportQuery {
+            limit: self.limit(),
+            group: self.group,
+            tournament: self.tournament,
+            before: self.before,
+        }
+    }
+}
+
+/// Page of the submissions, starting from the most recent one.
+#[derive(Serialize)]
+pub struct SubmissionPage {
+    pub submissions: Vec<Submission>,
+
+    /// Value of `before` for the next page, or `None` if this page is the last one.
+    pub next: Option<i64>,
+}
+
+/// Latest report submitted by the student to the tournament.
+#[derive(Serialize)]
+pub struct Submission {
+    pub report_id: i64,
+    pub created_at: String,
+    pub student_id: String,
+    pub student_name: String,
+    pub group: String,
+    pub tournament: String,
+    pub teacher: String,
+    pub white: SubmittedGame,
+    pub black: SubmittedGame,
+    pub flags: Vec<Flag>,
+    pub download_url: String,
+}
+
+/// Game played by the student with one of the colours.
+#[derive(Serialize)]
+pub struct SubmittedGame {
+    pub game_id: String,
+
+    /// Points scored by the student: `1`, `½` or `0`, or `None` if the game has no result.
+    pub points: Option<String>,
+
+    /// Number of full moves.
+    pub moves: usize,
+}
+
+/// Summary of the games of the report shown on the dashboard. It is computed when the report is
+/// archived, so the dashboard does not parse the games on each request.
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct ReportSummary {
+    pub white: GameSummary,
+    pub black: GameSummary,
+
+    /// The games were played by different accounts, see [`Flag::WrongColor`].
+    pub wrong_color: bool,
+}
+
+/// Summary of the game from the point of view of the student.
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct GameSummary {
+    pub points: Option<String>,
+    pub moves: usize,
+}
+
+impl ReportSummary {
+    /// Summarizes the games played by the student as White and as Black.
+    pub fn of(game_white: &Game, game_black: &Game) -> Self {
+        Self {
+            white: GameSummary::of(game_white, true),
+            black: GameSummary::of(game_black, false),
+            wrong_color: !same_player(game_white.header("White"), game_black.header("Black")),
+        }
+    }
+}
+
+impl GameSummary {
+    fn of(game: &Game, as_white: bool) -> Self {
+        let points = GameResult::of(game)
+            .points()
+            .map(|(white, black)| if as_white { white } else { black });
+
+        Self {
+            points: points.map(String::from),
+            moves: game.moves.len().div_ceil(2),
+        }
+    }
+}
+
+/// Issue with the submission that needs the teacher's attention.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    /// Both links point to the same game, or another student submitted the game played with the
+    /// same colour. The opponent submitting the game with the other colour is not a duplicate.
+    DuplicateGame,
+
+    /// The games were played by different accounts, so the student played the wrong colour in
+    /// one of them.
+    WrongColor,
+
+    /// One of the games is shorter than [`SHORT_GAME_MOVES`].
+    ShortGame,
+}
+
+/// Builds a page of the submissions from the latest archived reports, sorted from the most recent
+/// one, and all claims of their games. The page is full if it has `limit` reports, so there may be
+/// more of them.
+pub fn page(reports: &[StoredReport], claims: &[Claim], limit: u32) -> SubmissionPage {
+    let next = match reports.last() {
+        Some(last) if reports.len() >= limit as usize => Some(last.id),
+        _ => None,
+    };
+
+    SubmissionPage {
+        submissions: submissions(reports, claims),
+        next,
+    }
+}
+
+/// Builds the submissions from the archived reports. The claims are used to detect the games
+/// submitted by several students.
+pub fn submissions(reports: &[StoredReport], claims: &[Claim]) -> Vec<Submission> {
+    // Students who submitted each game with each colour.
+    let mut students: HashMap<(&str, bool), HashSet<&str>> = HashMap::new();
+    for claim in claims {
+        students
+            .entry((&claim.game_id, claim.as_white))
+            .or_default()
+            .insert(claim.student_id.trim());
+    }
+
+    reports
+        .iter()
+        .map(|report| {
+            let summary = &report.summary;
+            let mut flags = Vec::new();
+
+            let duplicate = report.white_game_id == report.black_game_id
+                || [
+                    (&*report.white_game_id, true),
+                    (&*report.black_game_id, false),
+                ]
+                .iter()
+                .any(|key| students.get(key).is_some_and(|students| students.len() > 1));
+            if duplicate {
+                flags.push(Flag::DuplicateGame);
+            }
+
+            if summary.wrong_color {
+                flags.push(Flag::WrongColor);
+            }
+
+            if summary.white.moves < SHORT_GAME_MOVES || summary.black.moves < SHORT_GAME_MOVES {
+                flags.push(Flag::ShortGame);
+            }
+
+            let white = SubmittedGame {
+                game_id: report.white_game_id.clone(),
+                points: summary.white.points.clone(),
+                moves: summary.white.moves,
+            };
+            let black = SubmittedGame {
+                game_id: report.black_game_id.clone(),
+                points: summary.black.points.clone(),
+                moves: summary.black.moves,
+            };
+
+            Submission {
+                report_id: report.id,
+                created_at: report.created_at.clone(),
+                student_id: report.data.student.id.clone(),
+                student_name: report.data.student.name.clone(),
+                group: report.data.student.group.clone(),
+                tournament: report.data.subject.tournament.clone(),
+                teacher: report.data.subject.teacher.clone(),
+                white,
+                black,
+                flags,
+                download_url: format!("/api/v1/reports/{}", report.id),
+            }
+        })
+        .collect()
+}
+
+fn same_player(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Data;
+    use crate::i18n::Language;
+    use crate::pgn;
+
+    fn report(id: i64, student_id: &str, games: (&str, &str), moves: usize) -> StoredReport {
+        let mut data = Data::default();
+        data.student.id = String::from(student_id);
+
+        let game = GameSummary {
+            points: Some(String::from("1")),
+            moves,
+        };
+
+        StoredReport {
+            id,
+            created_at: String::new(),
+            data,
+            white_game_id: String::from(games.0),
+            black_game_id: String::from(games.1),
+            summary: ReportSummary {
+                white: game,
+                black: GameSummary::default(),
+                wrong_color: false,
+            },
+        }
+    }
+
+    fn claim(game_id: &str, as_white: bool, student_id: &str) -> Claim {
+        Claim {
+            game_id: String::from(game_id),
+            as_white,
+            student_id: String::from(student_id),
+        }
+    }
+
+    #[test]
+    fn flags_submissions() {
+        let mut wrong_color = report(3, "333", ("e", "f"), 30);
+        wrong_color.summary.black.moves = 30;
+        wrong_color.summary.wrong_color = true;
+
+        let reports = [
+            report(1, "111", ("a", "b"), 30),
+            report(2, "222", ("c", "c"), 30),
+            wrong_color,
+        ];
+        let claims = [
+            claim("a", true, "111"),
+            claim("a", true, " 444 "),
+            claim("b", false, "111"),
+            claim("b", true, "555"),
+        ];
+
+        let flags: Vec<_> = submissions(&reports, &claims)
+            .into_iter()
+            .map(|submission| submission.flags)
+            .collect();
+        assert!(
+            flags
+                == [
+                    vec![Flag::DuplicateGame, Flag::ShortGame],
+                    vec![Flag::DuplicateGame, Flag::ShortGame],
+                    vec![Flag::WrongColor],
+                ]
+        );
+    }
+
+    #[test]
+    fn pages_submissions() {
+        let reports = [
+            report(5, "111", ("a", "b"), 30),
+            report(3, "222", ("c", "d"), 30),
+        ];
+
+        assert_eq!(page(&reports, &[], 2).next, Some(3));
+        assert_eq!(page(&reports, &[], 3).next, None);
+        assert_eq!(page(&[], &[], 2).next, None);
+    }
+
+    #[test]
+    fn summarizes_games_of_student() {
+        let white = pgn::parse(
+            "[White \"Student\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1",
+            Language::default(),
+        )
+        .unwrap();
+        let black = pgn::parse(
+            "[Black \"student\"]\n[Result \"0-1\"]\n\n1. e4 e5 0-1",
+            Language::default(),
+        )
+        .unwrap();
+
+        let summary = ReportSummary::of(&white, &black);
+        assert_eq!(summary.white.points.as_deref(), Some("0"));
+        assert_eq!(summary.black.points.as_deref(), Some("1"));
+        assert_eq!((summary.white.moves, summary.black.moves), (2, 1));
+        assert!(!summary.wrong_color);
+
+        let other = pgn::parse("[Black \"Someone\"]\n\n1. e4 e5 *", Language::default()).unwrap();
+        assert!(ReportSummary::of(&white, &other).wrong_color);
+    }
+}
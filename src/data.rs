@@ -1,96 +1,533 @@
-use anyhow::bail;
-use pgnparse::parser::{PgnInfo, parse_pgn_to_rust_struct};
+use anyhow::anyhow;
 use reqwest::Url;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
 
-use crate::lichess;
+use crate::error::{ApiError, FieldError};
+use crate::i18n::Language;
+use crate::lichess::{self, ExportOptions, Lichess};
+use crate::name::PersonName;
+use crate::pgn::{self, Game};
+use crate::rules::RuleSets;
+use crate::validation::Formats;
+use crate::xlsx::{Clocks, Metadata, Notation};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Default, ToSchema)]
 pub struct StudentData {
+    /// Full name of the student: surname, given name and patronymic.
     pub name: String,
     pub group: String,
+
+    /// Student ID number.
     pub id: String,
+
+    /// Parts of the name overriding the ones taken from `name`, e.g. for double surnames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patronymic: Option<String>,
 }
 
 impl StudentData {
-    pub fn short_name(&self) -> String {
-        let mut parts: Vec<&str> = self.name.split(" ").collect();
+    fn validate(&self, formats: &Formats, lang: Language, errors: &mut Vec<FieldError>) {
+        formats
+            .student_name
+            .validate(&self.name, "student.name", lang, errors);
+        formats
+            .student_group
+            .validate(&self.group, "student.group", lang, errors);
+        formats
+            .student_id
+            .validate(&self.id, "student.id", lang, errors);
+
+        // The explicit parts replace the parsed name as a whole, so they cannot leave out the
+        // surname or the given name.
+        if self.has_explicit_name() {
+            for (part, field) in [
+                (&self.surname, "student.surname"),
+                (&self.given_name, "student.given_name"),
+            ] {
+                if explicit_part(part).is_none() {
+                    errors.push(FieldError::new(
+                        field,
+                        "required",
+                        lang.text("validation.required"),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn has_explicit_name(&self) -> bool {
+        [&self.surname, &self.given_name, &self.patronymic]
+            .into_iter()
+            .any(|part| explicit_part(part).is_some())
+    }
+
+    /// Returns the name of the student split into parts. If any part is given explicitly in the
+    /// request, the name is made of the explicit parts, otherwise it is parsed from `name`.
+    pub fn person_name(&self) -> PersonName {
+        if !self.has_explicit_name() {
+            return PersonName::parse(&self.name);
+        }
 
-        if parts.len() >= 3 {
-            parts.pop();
+        PersonName {
+            surname: explicit_part(&self.surname).unwrap_or_default(),
+            given_name: explicit_part(&self.given_name).unwrap_or_default(),
+            patronymic: explicit_part(&self.patronymic),
         }
+    }
+}
 
-        parts.join(" ")
+fn trim_in_place(value: &mut String) {
+    let trimmed = value.trim();
+    if trimmed.len() != value.len() {
+        *value = String::from(trimmed);
     }
 }
 
-#[derive(Deserialize)]
+fn explicit_part(part: &Option<String>) -> Option<String> {
+    part.as_deref()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(String::from)
+}
+
+#[derive(Serialize, Deserialize, Default, ToSchema)]
 pub struct SubjectData {
+    /// Full name of the teacher.
     pub teacher: String,
     pub tournament: String,
 }
 
-#[derive(Deserialize)]
+impl SubjectData {
+    fn validate(&self, formats: &Formats, lang: Language, errors: &mut Vec<FieldError>) {
+        formats
+            .subject_teacher
+            .validate(&self.teacher, "subject.teacher", lang, errors);
+        formats
+            .subject_tournament
+            .validate(&self.tournament, "subject.tournament", lang, errors);
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, ToSchema)]
 pub struct GameData {
+    /// Full name of the opponent.
     pub opponent: String,
+
+    /// Lichess URL of the game the student played as White.
+    #[schema(example = "https://lichess.org/q7ZvsdUF")]
     pub white_url: String,
+
+    /// Lichess URL of the game the student played as Black.
+    #[schema(example = "https://lichess.org/Hw5kH3pA")]
     pub black_url: String,
 }
 
 impl GameData {
-    fn validate(&self) -> anyhow::Result<()> {
-        let url = match Url::parse(&self.white_url) {
-            Ok(url) => url,
-            Err(_) => bail!("Ссылка на игру белыми невалидна"),
-        };
+    fn validate(&self, formats: &Formats, lang: Language, errors: &mut Vec<FieldError>) {
+        formats
+            .game_opponent
+            .validate(&self.opponent, "game.opponent", lang, errors);
+        validate_game_url(
+            &self.white_url,
+            "game.white_url",
+            "color.white",
+            lang,
+            errors,
+        );
+        validate_game_url(
+            &self.black_url,
+            "game.black_url",
+            "color.black",
+            lang,
+            errors,
+        );
+    }
 
-        if lichess::game_url_to_export_url(&url).is_none() {
-            bail!("Неверный формат ссылки на игру белыми")
-        }
+    /// Returns Lichess IDs of the games played as White and as Black.
+    pub fn game_ids(&self) -> anyhow::Result<(String, String)> {
+        Ok((game_id(&self.white_url)?, game_id(&self.black_url)?))
+    }
+}
 
-        let url = match Url::parse(&self.black_url) {
-            Ok(url) => url,
-            Err(_) => bail!("Ссылка на игру черными невалидна"),
-        };
+fn game_id(game_url: &str) -> anyhow::Result<String> {
+    let url = Url::parse(game_url)?;
+    lichess::game_id(&url)
+        .map(String::from)
+        .ok_or(anyhow!("cannot extract game ID"))
+}
 
-        if lichess::game_url_to_export_url(&url).is_none() {
-            bail!("Неверный формат ссылки на игру черными")
-        }
+fn validate_game_url(
+    game_url: &str,
+    field: &'static str,
+    color: &str,
+    lang: Language,
+    errors: &mut Vec<FieldError>,
+) {
+    let color = lang.text(color);
+
+    match Url::parse(game_url) {
+        Ok(url) if !lichess::is_lichess_url(&url) => errors.push(FieldError::new(
+            field,
+            "not_lichess_url",
+            lang.format("validation.not_lichess_url", &[("color", &color)]),
+        )),
+        Ok(url) if lichess::game_url_to_export_url(&url, ExportOptions::default()).is_some() => {}
+        Ok(_) => errors.push(FieldError::new(
+            field,
+            "invalid_game_url",
+            lang.format("validation.invalid_game_url", &[("color", &color)]),
+        )),
+        Err(_) => errors.push(FieldError::new(
+            field,
+            "invalid_url",
+            lang.format("validation.invalid_url", &[("color", &color)]),
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, ToSchema)]
+#[serde(default)]
+pub struct OptionsData {
+    pub notation: Notation,
+
+    /// Write the moves in the long algebraic notation, e.g. `e2-e4`.
+    pub long_algebraic: bool,
+    pub clocks: Clocks,
+
+    /// Include the evaluations from the Lichess analysis.
+    pub evals: bool,
+
+    /// Analyse the games with the local engine.
+    pub analysis: bool,
+
+    /// Game headers shown in the report.
+    pub metadata: Vec<Metadata>,
 
-        Ok(())
+    /// Language of the report and the error messages. Without it, the language is picked from
+    /// the `Accept-Language` header.
+    pub language: Option<Language>,
+}
+
+impl OptionsData {
+    /// Returns whether the report contains evaluations, either from Lichess or from the local
+    /// engine.
+    pub fn show_evals(&self) -> bool {
+        self.evals || self.analysis
+    }
+
+    pub fn language(&self) -> Language {
+        self.language.unwrap_or_default()
     }
 }
 
-#[derive(Deserialize)]
+/// Games uploaded as PGN instead of the links to Lichess.
+#[derive(Default)]
+pub struct UploadedGames {
+    pub white: Option<String>,
+    pub black: Option<String>,
+}
+
+/// Uploaded games parsed once, when their links are taken from the `Site` headers.
+#[derive(Default)]
+pub struct ParsedUploads {
+    pub white: Option<Game>,
+    pub black: Option<Game>,
+}
+
+/// Request to generate a report.
+#[derive(Serialize, Deserialize, Default, ToSchema)]
 pub struct Data {
     pub student: StudentData,
     pub subject: SubjectData,
     pub game: GameData,
+    #[serde(default)]
+    pub options: OptionsData,
 }
 
 impl Data {
-    pub fn validate(&self) -> anyhow::Result<()> {
-        self.game.validate()?;
+    /// Normalizes the fields and validates them, returning all found errors at once. The trimmed
+    /// values replace the original ones, so they are used in the report, the archive and the
+    /// submission index.
+    pub fn validate(&mut self, formats: &Formats) -> Result<(), ApiError> {
+        self.normalize();
+
+        let lang = self.options.language();
+        let mut errors = Vec::new();
+
+        self.student.validate(formats, lang, &mut errors);
+        self.subject.validate(formats, lang, &mut errors);
+        self.game.validate(formats, lang, &mut errors);
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(ApiError::validation(errors, lang)),
+        }
+    }
+
+    /// Trims the text fields and removes the repeated metadata headers, so that each header is
+    /// shown once.
+    fn normalize(&mut self) {
+        let Self {
+            student,
+            subject,
+            game,
+            options,
+        } = self;
+
+        let mut metadata = Vec::with_capacity(options.metadata.len());
+        for header in options.metadata.drain(..) {
+            if !metadata.contains(&header) {
+                metadata.push(header);
+            }
+        }
+        options.metadata = metadata;
+
+        for field in [
+            &mut student.name,
+            &mut student.group,
+            &mut student.id,
+            &mut subject.teacher,
+            &mut subject.tournament,
+            &mut game.opponent,
+            &mut game.white_url,
+            &mut game.black_url,
+        ] {
+            trim_in_place(field);
+        }
+
+        for part in [
+            &mut student.surname,
+            &mut student.given_name,
+            &mut student.patronymic,
+        ] {
+            *part = explicit_part(part);
+        }
+    }
+
+    /// Parses the uploaded games and takes their links from the `Site` headers, so that the games
+    /// are validated, registered and archived the same way as the downloaded ones. The links are
+    /// replaced with the lichess.org ones the games are verified against.
+    pub fn parse_uploads(&mut self, uploads: &UploadedGames) -> Result<ParsedUploads, ApiError> {
+        let lang = self.options.language();
+        let white = uploads
+            .white
+            .as_deref()
+            .map(|pgn| self.parse_upload(pgn, "game.white_pgn", "color.white"));
+        let black = uploads
+            .black
+            .as_deref()
+            .map(|pgn| self.parse_upload(pgn, "game.black_pgn", "color.black"));
+
+        let mut errors = Vec::new();
+        let mut parsed = ParsedUploads::default();
+        for (upload, field, game) in [
+            (white, &mut self.game.white_url, &mut parsed.white),
+            (black, &mut self.game.black_url, &mut parsed.black),
+        ] {
+            match upload {
+                Some(Ok((url, upload))) => {
+                    *field = url;
+                    *game = Some(upload);
+                }
+                Some(Err(e)) => errors.push(e),
+                None => {}
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(parsed),
+            false => Err(ApiError::validation(errors, lang)),
+        }
+    }
+
+    /// Loads both games, replays them to make sure that all moves are legal and checks them
+    /// against the rules of the tournament. The uploaded games are verified against the games on
+    /// Lichess instead of being used in place of them.
+    pub async fn load_games(
+        &self,
+        lichess: &Lichess,
+        rules: &RuleSets,
+        uploads: ParsedUploads,
+    ) -> Result<(Game, Game), FieldError> {
+        let white = async {
+            match uploads.white {
+                Some(game) => self.verify_upload_as_white(game, lichess, rules).await,
+                None => self.load_game_as_white(lichess, rules).await,
+            }
+        };
+        let black = async {
+            match uploads.black {
+                Some(game) => self.verify_upload_as_black(game, lichess, rules).await,
+                None => self.load_game_as_black(lichess, rules).await,
+            }
+        };
+
+        tokio::try_join!(white, black)
+    }
+
+    async fn load_game_as_white(
+        &self,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, FieldError> {
+        self.load_game(&self.game.white_url, lichess, rules)
+            .await
+            .map_err(|(code, e)| self.game_error("game.white_url", "color.white", code, e))
+    }
 
-        Ok(())
+    async fn load_game_as_black(
+        &self,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, FieldError> {
+        self.load_game(&self.game.black_url, lichess, rules)
+            .await
+            .map_err(|(code, e)| self.game_error("game.black_url", "color.black", code, e))
     }
 
-    pub async fn load_game_as_white(&self) -> anyhow::Result<PgnInfo> {
-        self.load_game(&self.game.white_url).await
+    async fn verify_upload_as_white(
+        &self,
+        game: Game,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, FieldError> {
+        self.verify_upload(game, &self.game.white_url, lichess, rules)
+            .await
+            .map_err(|(code, e)| self.game_error("game.white_pgn", "color.white", code, e))
     }
 
-    pub async fn load_game_as_black(&self) -> anyhow::Result<PgnInfo> {
-        self.load_game(&self.game.black_url).await
+    async fn verify_upload_as_black(
+        &self,
+        game: Game,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, FieldError> {
+        self.verify_upload(game, &self.game.black_url, lichess, rules)
+            .await
+            .map_err(|(code, e)| self.game_error("game.black_pgn", "color.black", code, e))
     }
 
-    async fn load_game(&self, game_url: &str) -> anyhow::Result<PgnInfo> {
-        let url = Url::parse(game_url)?;
-        let export_url = match lichess::game_url_to_export_url(&url) {
-            Some(url) => url,
-            None => bail!("cannot extract game export URL"),
+    /// Loads the game. The error is returned with the error code of the failed stage.
+    #[instrument(skip_all, fields(game_id = game_id(game_url).ok()))]
+    async fn load_game(
+        &self,
+        game_url: &str,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, (&'static str, anyhow::Error)> {
+        let game = self
+            .fetch_game(game_url, lichess)
+            .await
+            .and_then(|game| self.check_rules(game, rules));
+
+        if let Err((code, e)) = &game {
+            tracing::warn!(code, error = %e, "cannot load game");
+        }
+
+        game
+    }
+
+    /// Checks that the uploaded game is the one on Lichess with the ID from its `Site` header, so
+    /// that a game edited or made up offline cannot pass the rules and the duplicate check. The
+    /// report is built from the game on Lichess, so the clocks and the evaluations come from
+    /// there as well.
+    #[instrument(skip_all, fields(game_id = game_id(game_url).ok()))]
+    async fn verify_upload(
+        &self,
+        game: Game,
+        game_url: &str,
+        lichess: &Lichess,
+        rules: &RuleSets,
+    ) -> Result<Game, (&'static str, anyhow::Error)> {
+        let lang = self.options.language();
+        let original = self.fetch_game(game_url, lichess).await;
+
+        let game = original.and_then(|original| match game.matches(&original) {
+            true => self.check_rules(original, rules),
+            false => Err((
+                "game_mismatch",
+                anyhow!(lang.format("pgn.mismatch", &[("url", &game_url)])),
+            )),
+        });
+
+        if let Err((code, e)) = &game {
+            tracing::warn!(code, error = %e, "cannot verify uploaded game");
+        }
+
+        game
+    }
+
+    /// Downloads the game with the annotations requested in the options and parses it.
+    async fn fetch_game(
+        &self,
+        game_url: &str,
+        lichess: &Lichess,
+    ) -> Result<Game, (&'static str, anyhow::Error)> {
+        let options = ExportOptions {
+            clocks: self.options.clocks.is_enabled(),
+            evals: self.options.evals,
         };
-        let pgn = reqwest::get(export_url).await?.text().await?;
 
-        Ok(parse_pgn_to_rust_struct(pgn))
+        match lichess.fetch_pgn(game_url, options).await {
+            Ok(pgn) => pgn::parse(&pgn, self.options.language()).map_err(|e| ("invalid_game", e)),
+            Err(e) => Err(("game_unavailable", e)),
+        }
+    }
+
+    fn check_rules(
+        &self,
+        game: Game,
+        rules: &RuleSets,
+    ) -> Result<Game, (&'static str, anyhow::Error)> {
+        rules
+            .for_tournament(&self.subject.tournament)
+            .check(&game, self.options.language())
+            .map_err(|e| ("rules_violation", e))?;
+
+        Ok(game)
+    }
+
+    fn parse_upload(
+        &self,
+        pgn: &str,
+        field: &'static str,
+        color: &str,
+    ) -> Result<(String, Game), FieldError> {
+        let lang = self.options.language();
+        let game =
+            pgn::parse(pgn, lang).map_err(|e| self.game_error(field, color, "invalid_game", e))?;
+
+        // The game is verified against lichess.org whatever the domain in the header is.
+        match game.header("Site").and_then(|url| game_id(url).ok()) {
+            Some(id) => Ok((lichess::game_url(&id), game)),
+            None => Err(self.game_error(
+                field,
+                color,
+                "invalid_game",
+                anyhow!(lang.text("pgn.no_site")),
+            )),
+        }
+    }
+
+    fn game_error(
+        &self,
+        field: &'static str,
+        color: &str,
+        code: &'static str,
+        error: anyhow::Error,
+    ) -> FieldError {
+        let lang = self.options.language();
+        let message = lang.format(
+            "validation.game_error",
+            &[("color", &lang.text(color)), ("error", &error)],
+        );
+
+        FieldError::new(field, code, message)
     }
 }
@@ -0,0 +1,355 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use shakmaty::Color;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::{Instant, timeout, timeout_at};
+
+use crate::config::EngineConfig;
+use crate::eval::Eval;
+use crate::pgn::Game;
+
+/// Analysis stops once the time left for a single position becomes shorter than this.
+const MIN_MOVE_TIME: Duration = Duration::from_millis(20);
+
+/// Time the engine has to exit after the analysis before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Local chess engine speaking the UCI protocol, e.g. Stockfish.
+pub struct Engine {
+    path: PathBuf,
+    time_budget: Duration,
+}
+
+impl Engine {
+    pub fn new(path: impl Into<PathBuf>, time_budget: Duration) -> Self {
+        Self {
+            path: path.into(),
+            time_budget,
+        }
+    }
+
+    /// Configures the engine. Returns `None` if the engine path is not set.
+    pub fn from_config(config: &EngineConfig) -> Option<Self> {
+        let path = config.path.as_ref()?;
+
+        Some(Self::new(
+            path,
+            Duration::from_millis(config.time_budget_ms),
+        ))
+    }
+
+    /// Checks that the engine executable exists.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if !self.path.is_file() {
+            bail!("engine {} not found", self.path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates positions after the moves that have no evaluation yet.
+    ///
+    /// The whole analysis, including the start of the engine, takes no longer than the time
+    /// budget. The positions that the engine did not get to are left without evaluation. An
+    /// engine that does not respond in time fails the analysis.
+    pub async fn analyse(&self, game: &mut Game) -> anyhow::Result<()> {
+        let deadline = Instant::now() + self.time_budget;
+
+        let mut session = timeout_at(deadline, Session::start(&self.path))
+            .await
+            .map_err(|_| anyhow!("engine did not start in time"))??;
+
+        timeout_at(deadline, session.analyse(game, deadline))
+            .await
+            .map_err(|_| anyhow!("engine did not respond in time"))??;
+
+        session.quit().await
+    }
+}
+
+/// Running engine process.
+struct Session {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Session {
+    async fn start(path: &Path) -> anyhow::Result<Self> {
+        let mut process = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = process
+            .stdin
+            .take()
+            .ok_or(anyhow!("cannot open engine stdin"))?;
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or(anyhow!("cannot open engine stdout"))?;
+
+        let mut session = Self {
+            process,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        };
+
+        session.send("uci").await?;
+        session.wait_for("uciok").await?;
+
+        Ok(session)
+    }
+
+    /// Evaluates the positions of the game one by one, splitting the time left until the
+    /// deadline between them.
+    async fn analyse(&mut self, game: &mut Game, deadline: Instant) -> anyhow::Result<()> {
+        if game.header("Variant") == Some("Chess960") {
+            self.send("setoption name UCI_Chess960 value true").await?;
+        }
+        self.send("ucinewgame").await?;
+        self.wait_ready().await?;
+
+        let start = match game.header("FEN") {
+            Some(fen) => format!("position fen {fen} moves"),
+            None => String::from("position startpos moves"),
+        };
+
+        let pending: Vec<usize> = (0..game.moves.len())
+            .filter(|&ply| game.moves[ply].eval.is_none() && !game.moves[ply].san.ends_with('#'))
+            .collect();
+
+        for (i, &ply) in pending.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let move_time = remaining / (pending.len() - i) as u32;
+
+            if move_time < MIN_MOVE_TIME {
+                break;
+            }
+
+            let moves: Vec<&str> = game.moves[..=ply].iter().map(|m| m.uci.as_str()).collect();
+            self.send(&format!("{start} {}", moves.join(" "))).await?;
+
+            let score = self.go(move_time).await?;
+
+            // The engine reports the score from the point of view of the side to move, which is
+            // Black after White's move.
+            let m = &mut game.moves[ply];
+            m.eval = score.map(|eval| match (m.color, eval) {
+                (Color::White, Eval::Centipawns(cp)) => Eval::Centipawns(-cp),
+                (Color::White, Eval::Mate(moves)) => Eval::Mate(-moves),
+                (Color::Black, eval) => eval,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn send(&mut self, command: &str) -> anyhow::Result<()> {
+        self.stdin.write_all(command.as_bytes()).await?;
+        self.stdin.write_all(b"\n").await?;
+        self.stdin.flush().await?;
+
+        Ok(())
+    }
+
+    async fn wait_ready(&mut self) -> anyhow::Result<()> {
+        self.send("isready").await?;
+        self.wait_for("readyok").await
+    }
+
+    async fn wait_for(&mut self, response: &str) -> anyhow::Result<()> {
+        while let Some(line) = self.stdout.next_line().await? {
+            if line.trim() == response {
+                return Ok(());
+            }
+        }
+
+        bail!("engine exited before sending {response}")
+    }
+
+    /// Searches the current position and returns the last reported score.
+    async fn go(&mut self, move_time: Duration) -> anyhow::Result<Option<Eval>> {
+        self.send(&format!("go movetime {}", move_time.as_millis()))
+            .await?;
+
+        let mut score = None;
+
+        while let Some(line) = self.stdout.next_line().await? {
+            if line.starts_with("bestmove") {
+                return Ok(score);
+            }
+
+            if line.starts_with("info") {
+                score = parse_score(&line).or(score);
+            }
+        }
+
+        bail!("engine exited before sending bestmove")
+    }
+
+    /// Asks the engine to exit, killing it if it does not exit within [`QUIT_TIMEOUT`].
+    async fn quit(mut self) -> anyhow::Result<()> {
+        self.send("quit").await?;
+
+        if timeout(QUIT_TIMEOUT, self.process.wait()).await.is_err() {
+            tracing::warn!("engine did not exit in time");
+            self.process.kill().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `score cp <x>` or `score mate <y>` from the `info` line.
+fn parse_score(info: &str) -> Option<Eval> {
+    let mut tokens = info
+        .split_whitespace()
+        .skip_while(|&token| token != "score");
+    tokens.next()?;
+
+    let kind = tokens.next()?;
+    let value = tokens.next()?.parse().ok()?;
+
+    match kind {
+        "cp" => Some(Eval::Centipawns(value)),
+        "mate" => Some(Eval::Mate(value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Language;
+    use crate::pgn;
+
+    fn fake_engine(time_budget: Duration) -> Engine {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/fake-uci-engine.sh"
+        );
+        Engine::new(path, time_budget)
+    }
+
+    fn game() -> Game {
+        pgn::parse("1. e4 e5 2. Nf3 Nc6 *", Language::default()).unwrap()
+    }
+
+    #[test]
+    fn parses_score() {
+        assert_eq!(
+            parse_score("info depth 12 seldepth 16 score cp -35 nodes 1000 pv e2e4"),
+            Some(Eval::Centipawns(-35))
+        );
+        assert_eq!(
+            parse_score("info depth 5 score mate 2 pv d1h5"),
+            Some(Eval::Mate(2))
+        );
+        assert_eq!(parse_score("info string NNUE enabled"), None);
+    }
+
+    #[tokio::test]
+    async fn evaluates_from_white_point_of_view() {
+        let mut game = pgn::parse("1. e4 e5 2. Nf3 Nc6 *", Language::default()).unwrap();
+
+        fake_engine(Duration::from_secs(2))
+            .analyse(&mut game)
+            .await
+            .unwrap();
+
+        // The fake engine always reports +0.50 for the side to move.
+        let evals: Vec<_> = game.moves.iter().map(|m| m.eval).collect();
+        assert_eq!(
+            evals,
+            [
+                Some(Eval::Centipawns(-50)),
+                Some(Eval::Centipawns(50)),
+                Some(Eval::Centipawns(-50)),
+                Some(Eval::Centipawns(50)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn evaluates_games_starting_with_black_move() {
+        let mut game = pgn::parse(
+            "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 2. e4 *",
+            Language::default(),
+        )
+        .unwrap();
+
+        fake_engine(Duration::from_secs(2))
+            .analyse(&mut game)
+            .await
+            .unwrap();
+
+        let evals: Vec<_> = game.moves.iter().map(|m| m.eval).collect();
+        assert_eq!(
+            evals,
+            [Some(Eval::Centipawns(50)), Some(Eval::Centipawns(-50))]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_existing_evals_and_skips_checkmate() {
+        let mut game = pgn::parse(
+            "1. e4 { [%eval 0.3] } 1... e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0",
+            Language::default(),
+        )
+        .unwrap();
+
+        fake_engine(Duration::from_secs(2))
+            .analyse(&mut game)
+            .await
+            .unwrap();
+
+        assert_eq!(game.moves[0].eval, Some(Eval::Centipawns(30)));
+        assert_eq!(game.moves[1].eval, Some(Eval::Centipawns(50)));
+        assert_eq!(game.moves[6].eval, None);
+    }
+
+    #[tokio::test]
+    async fn stops_when_time_budget_is_exhausted() {
+        let mut game = pgn::parse("1. e4 e5 2. Nf3 Nc6 *", Language::default()).unwrap();
+
+        fake_engine(MIN_MOVE_TIME).analyse(&mut game).await.unwrap();
+
+        assert!(game.moves.iter().all(|m| m.eval.is_none()));
+    }
+
+    #[tokio::test]
+    async fn fails_when_engine_does_not_start() {
+        // `cat` echoes the commands, so it never answers `uci` with `uciok`.
+        let engine = Engine::new("cat", Duration::from_millis(200));
+        let started = Instant::now();
+
+        let error = engine.analyse(&mut game()).await.unwrap_err();
+
+        assert_eq!(error.to_string(), "engine did not start in time");
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn kills_engine_that_does_not_exit() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/stuck-uci-engine.sh"
+        );
+        let engine = Engine::new(path, Duration::from_millis(200));
+        let mut game = game();
+        let started = Instant::now();
+
+        engine.analyse(&mut game).await.unwrap();
+
+        assert!(game.moves.iter().any(|m| m.eval.is_some()));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
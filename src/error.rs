@@ -0,0 +1,128 @@
+use axum::{
+    Json,
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::i18n::Language;
+
+/// Error returned by the API as a JSON body.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+
+    /// Machine-readable error code, e.g. `validation_failed`.
+    pub code: &'static str,
+
+    /// Message to be shown to the user.
+    pub message: String,
+
+    /// Errors of the individual request fields.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// Error of a single request field.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FieldError {
+    /// Path of the field in the request body, e.g. `game.white_url`.
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn unauthorized(lang: Language) -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            lang.text("error.unauthorized"),
+        )
+    }
+
+    pub fn forbidden(lang: Language) -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "forbidden",
+            lang.text("error.forbidden"),
+        )
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn internal(error: impl ToString) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            error.to_string(),
+        )
+    }
+
+    /// Creates an error with all invalid fields of the request. The message of the error is the
+    /// message of the field if it is the only one.
+    pub fn validation(errors: Vec<FieldError>, lang: Language) -> Self {
+        let message = match errors.as_slice() {
+            [error] => error.message.clone(),
+            _ => String::from(lang.text("error.validation_failed")),
+        };
+
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code: "validation_failed",
+            message,
+            errors,
+        }
+    }
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<FieldError> for ApiError {
+    fn from(error: FieldError) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code: "validation_failed",
+            message: error.message.clone(),
+            errors: vec![error],
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "invalid_request", rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
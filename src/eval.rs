@@ -0,0 +1,237 @@
+use std::fmt;
+
+use shakmaty::Color;
+
+use crate::pgn::Game;
+
+/// Engine evaluation of a position from White's point of view.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Eval {
+    Centipawns(i32),
+
+    /// Forced mate in the given number of moves. Negative if Black is the one to mate.
+    Mate(i32),
+}
+
+impl Eval {
+    /// Parses evaluation in the `[%eval]` format: pawns (`0.17`, `-1.5`) or mate (`#3`, `#-2`).
+    pub fn parse(eval: &str) -> Option<Self> {
+        match eval.strip_prefix('#') {
+            Some(mate) => mate.parse().ok().map(Self::Mate),
+            None => {
+                let pawns: f64 = eval.parse().ok()?;
+                pawns
+                    .is_finite()
+                    .then(|| Self::Centipawns((pawns * 100.0).round() as i32))
+            }
+        }
+    }
+
+    /// Returns White's winning chances in range from -1 to 1, as calculated by Lichess.
+    pub fn winning_chances(&self) -> f64 {
+        let centipawns = match *self {
+            Self::Centipawns(cp) => cp.clamp(-1000, 1000),
+            Self::Mate(moves) => (21 - moves.unsigned_abs().min(10) as i32) * 100 * moves.signum(),
+        };
+
+        2.0 / (1.0 + (-0.00368208 * centipawns as f64).exp()) - 1.0
+    }
+}
+
+impl fmt::Display for Eval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Centipawns(cp) => write!(f, "{:+.1}", cp as f64 / 100.0),
+            Self::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+/// Quality of a move that worsened the player's position.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// Judges the half-move with the given index by the loss of the mover's winning chances. The
+    /// thresholds are the same as in the Lichess analysis.
+    pub fn of(game: &Game, ply: usize) -> Option<Self> {
+        let loss = winning_chances_loss(game, ply)?;
+
+        if loss >= 0.3 {
+            Some(Self::Blunder)
+        } else if loss >= 0.2 {
+            Some(Self::Mistake)
+        } else if loss >= 0.1 {
+            Some(Self::Inaccuracy)
+        } else {
+            None
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Inaccuracy => "?!",
+            Self::Mistake => "?",
+            Self::Blunder => "??",
+        }
+    }
+}
+
+/// Analysis summary of one player's moves.
+#[derive(Default)]
+pub struct Summary {
+    /// Average accuracy of the moves in percent, if any move has evaluation.
+    pub accuracy: Option<f64>,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+impl Summary {
+    /// Summarizes moves of the player of the given color.
+    pub fn of(game: &Game, color: Color) -> Self {
+        let mut summary = Self::default();
+        let mut accuracies = Vec::new();
+
+        for ply in (0..game.moves.len()).filter(|&ply| game.moves[ply].color == color) {
+            if let Some(loss) = winning_chances_loss(game, ply) {
+                // Lichess move accuracy formula, with the win percentage being 50 + 50 * chances.
+                let accuracy = 103.1668 * (-0.04354 * loss * 50.0).exp() - 3.1669;
+                accuracies.push(accuracy.clamp(0.0, 100.0));
+            }
+
+            match Judgement::of(game, ply) {
+                Some(Judgement::Inaccuracy) => summary.inaccuracies += 1,
+                Some(Judgement::Mistake) => summary.mistakes += 1,
+                Some(Judgement::Blunder) => summary.blunders += 1,
+                None => {}
+            }
+        }
+
+        if !accuracies.is_empty() {
+            summary.accuracy = Some(accuracies.iter().sum::<f64>() / accuracies.len() as f64);
+        }
+
+        summary
+    }
+}
+
+/// Returns indices of up to `count` half-moves that lost the most winning chances, starting from
+/// the worst one. Only the moves judged as inaccuracies or worse are included.
+pub fn biggest_mistakes(game: &Game, count: usize) -> Vec<usize> {
+    let mut mistakes: Vec<(usize, f64)> = (0..game.moves.len())
+        .filter(|&ply| Judgement::of(game, ply).is_some())
+        .filter_map(|ply| Some((ply, winning_chances_loss(game, ply)?)))
+        .collect();
+
+    mistakes.sort_by(|a, b| b.1.total_cmp(&a.1));
+    mistakes
+        .into_iter()
+        .take(count)
+        .map(|(ply, _)| ply)
+        .collect()
+}
+
+/// Returns how much the half-move decreased the mover's winning chances.
+fn winning_chances_loss(game: &Game, ply: usize) -> Option<f64> {
+    let m = game.moves.get(ply)?;
+    let after = m.eval?;
+    let before = match ply.checked_sub(1) {
+        Some(previous) => game.moves[previous].eval?,
+        None => Eval::Centipawns(0),
+    };
+
+    let loss = before.winning_chances() - after.winning_chances();
+
+    Some(
+        match m.color {
+            Color::White => loss,
+            Color::Black => -loss,
+        }
+        .max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Language;
+    use crate::pgn;
+
+    /// Game with the given evaluations after each half-move of a fixed opening.
+    fn game(evals: &[&str]) -> Game {
+        const MOVES: &[&str] = &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6"];
+
+        let movetext: Vec<_> = MOVES
+            .iter()
+            .zip(evals)
+            .map(|(san, eval)| format!("{san} {{ [%eval {eval}] }}"))
+            .collect();
+
+        pgn::parse(&format!("{} *", movetext.join(" ")), Language::En).unwrap()
+    }
+
+    #[test]
+    fn parses_evals() {
+        assert_eq!(Eval::parse("0.17"), Some(Eval::Centipawns(17)));
+        assert_eq!(Eval::parse("-1.505"), Some(Eval::Centipawns(-151)));
+        assert_eq!(Eval::parse("#3"), Some(Eval::Mate(3)));
+        assert_eq!(Eval::parse("#-2"), Some(Eval::Mate(-2)));
+        assert_eq!(Eval::parse("NaN"), None);
+        assert_eq!(Eval::parse("inf"), None);
+        assert_eq!(Eval::parse("#"), None);
+        assert_eq!(Eval::parse("#99999999999"), None);
+    }
+
+    #[test]
+    fn displays_evals() {
+        assert_eq!(Eval::Centipawns(17).to_string(), "+0.2");
+        assert_eq!(Eval::Centipawns(-150).to_string(), "-1.5");
+        assert_eq!(Eval::Mate(-2).to_string(), "#-2");
+    }
+
+    #[test]
+    fn calculates_winning_chances() {
+        assert_eq!(Eval::Centipawns(0).winning_chances(), 0.0);
+        assert_eq!(
+            Eval::Centipawns(5000).winning_chances(),
+            Eval::Centipawns(1000).winning_chances()
+        );
+        assert!(Eval::Mate(1).winning_chances() > Eval::Mate(5).winning_chances());
+        assert_eq!(
+            Eval::Mate(i32::MIN).winning_chances(),
+            -Eval::Mate(10).winning_chances()
+        );
+        assert!(Eval::Mate(i32::MAX).winning_chances() > 0.9);
+    }
+
+    #[test]
+    fn judges_moves() {
+        let game = game(&["0.2", "0.3", "0.1", "3.5", "-1.0", "#-1", "-0.5", "-0.3"]);
+
+        assert_eq!(Judgement::of(&game, 0), None);
+        assert_eq!(Judgement::of(&game, 2), None);
+        assert_eq!(Judgement::of(&game, 3), Some(Judgement::Blunder));
+        assert_eq!(Judgement::of(&game, 4), Some(Judgement::Blunder));
+        assert_eq!(Judgement::of(&game, 7), None);
+        assert_eq!(biggest_mistakes(&game, 2), vec![4, 3]);
+    }
+
+    #[test]
+    fn summarizes_accuracy() {
+        let perfect = Summary::of(&game(&["0.0", "0.0", "0.0", "0.0"]), Color::White);
+        assert!(perfect.accuracy.unwrap() > 99.9);
+
+        let blundered = game(&["0.2", "0.3", "0.1", "3.5", "-1.0", "#-1"]);
+        let white = Summary::of(&blundered, Color::White);
+        let black = Summary::of(&blundered, Color::Black);
+
+        assert_eq!((white.blunders, black.blunders), (1, 1));
+        assert!(white.accuracy.unwrap() < black.accuracy.unwrap());
+        assert!(Summary::of(&game(&[]), Color::White).accuracy.is_none());
+    }
+}
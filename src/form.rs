@@ -0,0 +1,307 @@
+use std::borrow::Cow;
+
+use axum::{
+    Form, Json,
+    extract::{FromRequest, Multipart, Request},
+    http::{StatusCode, header},
+};
+use serde_json::{Map, Value};
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+};
+
+use crate::data::{Data, GameData, OptionsData, StudentData, SubjectData, UploadedGames};
+use crate::error::ApiError;
+
+/// Options sent as checkboxes, which are present in the form only when checked.
+const CHECKBOX_OPTIONS: &[&str] = &["long_algebraic", "evals", "analysis"];
+
+/// Request to generate a report sent as JSON, as an HTML form, or as a multipart form with the
+/// games uploaded as PGN files.
+///
+/// The form fields are named by the paths of the JSON fields, e.g. `student.name`. Repeated
+/// `options.metadata` fields make up the list, and the PGN files are sent in `game.white_pgn`
+/// and `game.black_pgn`.
+pub struct ReportRequest {
+    pub data: Data,
+    pub uploads: UploadedGames,
+}
+
+/// Fields of the form collected into the JSON shape of [`Data`].
+struct FormFields {
+    data: Map<String, Value>,
+    uploads: UploadedGames,
+}
+
+impl<S: Send + Sync> FromRequest<S> for ReportRequest {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, ApiError> {
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let fields = match content_type.as_str() {
+            "application/x-www-form-urlencoded" => {
+                let Form(pairs) = Form::<Vec<(String, String)>>::from_request(request, state)
+                    .await
+                    .map_err(|e| invalid_request(e.status(), e.body_text()))?;
+
+                let mut fields = FormFields::new();
+                for (name, value) in pairs {
+                    fields.insert(&name, value);
+                }
+                fields
+            }
+            "multipart/form-data" => {
+                let mut multipart = Multipart::from_request(request, state)
+                    .await
+                    .map_err(|e| invalid_request(e.status(), e.body_text()))?;
+
+                let mut fields = FormFields::new();
+                while let Some(field) = multipart
+                    .next_field()
+                    .await
+                    .map_err(|e| invalid_request(e.status(), e.body_text()))?
+                {
+                    let name = field.name().unwrap_or_default().to_owned();
+                    let value = field
+                        .text()
+                        .await
+                        .map_err(|e| invalid_request(e.status(), e.body_text()))?;
+                    fields.insert(&name, value);
+                }
+                fields
+            }
+            _ => {
+                let Json(data) = Json::<Data>::from_request(request, state).await?;
+
+                return Ok(Self {
+                    data,
+                    uploads: UploadedGames::default(),
+                });
+            }
+        };
+
+        fields.into_request()
+    }
+}
+
+impl FormFields {
+    fn new() -> Self {
+        let sections = ["student", "subject", "game", "options"]
+            .map(|section| (String::from(section), Value::Object(Map::new())));
+
+        Self {
+            data: Map::from_iter(sections),
+            uploads: UploadedGames::default(),
+        }
+    }
+
+    /// Adds the field to its section. Fields outside the sections, e.g. the submit button, are
+    /// ignored, as are the empty files and options left unselected.
+    fn insert(&mut self, name: &str, value: String) {
+        let upload = match name {
+            "game.white_pgn" => Some(&mut self.uploads.white),
+            "game.black_pgn" => Some(&mut self.uploads.black),
+            _ => None,
+        };
+        if let Some(upload) = upload {
+            if !value.trim().is_empty() {
+                *upload = Some(value);
+            }
+            return;
+        }
+
+        let Some((section, key)) = name.split_once('.') else {
+            return;
+        };
+        let Some(Value::Object(fields)) = self.data.get_mut(section) else {
+            return;
+        };
+
+        if section != "options" {
+            fields.insert(String::from(key), Value::String(value));
+        } else if CHECKBOX_OPTIONS.contains(&key) {
+            let checked = matches!(value.as_str(), "on" | "true" | "1");
+            fields.insert(String::from(key), Value::Bool(checked));
+        } else if key == "metadata" {
+            if let Value::Array(metadata) = fields
+                .entry(key)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                metadata.push(Value::String(value));
+            }
+        } else if !value.is_empty() {
+            fields.insert(String::from(key), Value::String(value));
+        }
+    }
+
+    fn into_request(mut self) -> Result<ReportRequest, ApiError> {
+        // Links to the uploaded games are taken from the PGN later, so they may be left out.
+        if let Some(Value::Object(game)) = self.data.get_mut("game") {
+            for (upload, key) in [
+                (&self.uploads.white, "white_url"),
+                (&self.uploads.black, "black_url"),
+            ] {
+                if upload.is_some() {
+                    game.entry(key).or_insert(Value::String(String::new()));
+                }
+            }
+        }
+
+        let data = serde_json::from_value(Value::Object(self.data))
+            .map_err(|e| invalid_request(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+        Ok(ReportRequest {
+            data,
+            uploads: self.uploads,
+        })
+    }
+}
+
+fn invalid_request(status: StatusCode, message: impl Into<String>) -> ApiError {
+    ApiError::new(status, "invalid_request", message)
+}
+
+/// Describes the form fields by flattening the schemas of the JSON request.
+impl PartialSchema for ReportRequest {
+    fn schema() -> RefOr<Schema> {
+        let sections = [
+            ("student", StudentData::schema()),
+            ("subject", SubjectData::schema()),
+            ("game", GameData::schema()),
+            ("options", OptionsData::schema()),
+        ];
+
+        let mut form = ObjectBuilder::new().description(Some(
+            "Fields of the JSON request named by their paths, e.g. `student.name`. \
+             Checkboxes are checked with `on`, and `options.metadata` is repeated for each header.",
+        ));
+        for (section, schema) in sections {
+            let RefOr::T(Schema::Object(object)) = schema else {
+                continue;
+            };
+            for (name, property) in object.properties {
+                form = form.property(format!("{section}.{name}"), property);
+            }
+            for name in object.required {
+                form = form.required(format!("{section}.{name}"));
+            }
+        }
+
+        for (name, color) in [("game.white_pgn", "White"), ("game.black_pgn", "Black")] {
+            let file = ObjectBuilder::new()
+                .schema_type(Type::String)
+                .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)))
+                .description(Some(format!(
+                    "PGN of the game played as {color} replacing the link. The link is taken \
+                     from the `Site` header, and the moves and the main headers must match the \
+                     game on Lichess. The report is built from the game on Lichess."
+                )));
+            form = form.property(name, file);
+        }
+
+        RefOr::T(Schema::Object(form.build()))
+    }
+}
+
+impl ToSchema for ReportRequest {
+    fn name() -> Cow<'static, str> {
+        "ReportForm".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(pairs: &[(&str, &str)]) -> Result<ReportRequest, ApiError> {
+        let mut fields = FormFields::new();
+        for (name, value) in pairs {
+            fields.insert(name, String::from(*value));
+        }
+        fields.into_request()
+    }
+
+    const STUDENT: &[(&str, &str)] = &[
+        ("student.name", "Иванов Иван"),
+        ("student.group", "1234"),
+        ("student.id", "123456"),
+        ("subject.teacher", "Петров П.П."),
+        ("subject.tournament", "Турнир"),
+        ("game.opponent", "Сидоров Сидор"),
+    ];
+
+    #[test]
+    fn converts_form_fields() {
+        let pairs = [
+            ("game.white_url", "https://lichess.org/abcdefgh"),
+            ("game.black_url", "https://lichess.org/ijklmnop"),
+            ("options.notation", "figurine"),
+            ("options.evals", "on"),
+            ("options.analysis", "false"),
+            ("options.metadata", "time_control"),
+            ("options.metadata", "link"),
+            ("options.language", ""),
+            ("submit", ""),
+        ];
+        let ReportRequest { data, uploads } =
+            request(&[STUDENT, &pairs].concat()).unwrap_or_else(|e| panic!("{}", e.message));
+
+        assert_eq!(data.student.name, "Иванов Иван");
+        assert_eq!(data.game.black_url, "https://lichess.org/ijklmnop");
+        assert!(data.options.evals);
+        assert!(!data.options.analysis);
+        assert!(!data.options.long_algebraic);
+        assert_eq!(data.options.metadata.len(), 2);
+        assert_eq!(data.options.language, None);
+        assert!(uploads.white.is_none() && uploads.black.is_none());
+    }
+
+    #[test]
+    fn uploaded_games_replace_links() {
+        let pairs = [
+            ("game.white_pgn", "1. e4 e5 *"),
+            ("game.black_pgn", ""),
+            ("game.black_url", "https://lichess.org/ijklmnop"),
+        ];
+        let ReportRequest { data, uploads } =
+            request(&[STUDENT, &pairs].concat()).unwrap_or_else(|e| panic!("{}", e.message));
+
+        assert_eq!(data.game.white_url, "");
+        assert_eq!(uploads.white.as_deref(), Some("1. e4 e5 *"));
+        assert!(uploads.black.is_none());
+    }
+
+    #[test]
+    fn takes_lichess_links_of_uploads_from_site() {
+        let pairs = [
+            (
+                "game.white_pgn",
+                "[Site \"http://lichess.dev/abcdefghijkl\"]\n\n1. e4 e5 *",
+            ),
+            ("game.black_pgn", "1. d4 d5 *"),
+        ];
+        let ReportRequest { mut data, uploads } =
+            request(&[STUDENT, &pairs].concat()).unwrap_or_else(|e| panic!("{}", e.message));
+
+        let error = data.parse_uploads(&uploads).err().unwrap();
+        assert_eq!(data.game.white_url, "https://lichess.org/abcdefgh");
+        assert_eq!(error.errors.len(), 1);
+        assert_eq!(error.errors[0].field, "game.black_pgn");
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        let error = request(&[("student.name", "Иванов Иван")]).err().unwrap();
+
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.code, "invalid_request");
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+use axum::http::{HeaderMap, header};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+static RUSSIAN: LazyLock<HashMap<String, String>> =
+    LazyLock::new(|| load_catalog(include_str!("../static/i18n/ru.toml")));
+
+static ENGLISH: LazyLock<HashMap<String, String>> =
+    LazyLock::new(|| load_catalog(include_str!("../static/i18n/en.toml")));
+
+/// Language of the messages and the report.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Language {
+    /// Picks the most preferred supported language from the `Accept-Language` header, e.g.
+    /// `en-US,en;q=0.9,ru;q=0.8`.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut languages: Vec<(Self, f32)> = header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+
+                let language = match tag.split('-').next()?.to_ascii_lowercase().as_str() {
+                    "ru" => Self::Ru,
+                    "en" => Self::En,
+                    _ => return None,
+                };
+
+                Some((language, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+
+        // The sort is stable, so the languages with the same quality keep their order.
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+        languages.first().map(|(language, _)| *language)
+    }
+
+    /// Detects the language from the request headers, falling back to Russian.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+
+    /// Returns the message from the catalog of the language. Messages missing in the catalog are
+    /// taken from the Russian one, and the messages missing in both are replaced by the key.
+    pub fn text(self, key: &str) -> &str {
+        let catalog = match self {
+            Self::Ru => &*RUSSIAN,
+            Self::En => &*ENGLISH,
+        };
+
+        match catalog.get(key).or_else(|| RUSSIAN.get(key)) {
+            Some(message) => message,
+            None => {
+                tracing::error!(key, language = ?self, "missing message");
+                key
+            }
+        }
+    }
+
+    /// Returns the message with `{name}` placeholders replaced by the arguments.
+    pub fn format(self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(String::from(self.text(key)), |message, (name, value)| {
+                message.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+}
+
+/// Parses the catalog and flattens its tables into keys like `report.white`.
+fn load_catalog(content: &str) -> HashMap<String, String> {
+    fn flatten(prefix: &str, table: toml::Table, catalog: &mut HashMap<String, String>) {
+        for (key, value) in table {
+            let key = match prefix {
+                "" => key,
+                _ => format!("{prefix}.{key}"),
+            };
+
+            match value {
+                toml::Value::String(message) => {
+                    catalog.insert(key, message);
+                }
+                toml::Value::Table(table) => flatten(&key, table, catalog),
+                _ => panic!("message {key} is not a string"),
+            }
+        }
+    }
+
+    let mut catalog = HashMap::new();
+    flatten("", toml::from_str(content).unwrap(), &mut catalog);
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn catalogs_have_same_keys() {
+        let russian: BTreeSet<_> = RUSSIAN.keys().collect();
+        let english: BTreeSet<_> = ENGLISH.keys().collect();
+
+        assert!(
+            russian == english,
+            "only in ru.toml: {:?}, only in en.toml: {:?}",
+            russian.difference(&english).collect::<Vec<_>>(),
+            english.difference(&russian).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn falls_back_to_key() {
+        assert_eq!(Language::En.text("missing.message"), "missing.message");
+        assert_eq!(
+            Language::En.format("missing.{name}", &[("name", &"message")]),
+            "missing.message"
+        );
+    }
+
+    #[test]
+    fn picks_accepted_language() {
+        let pick = Language::from_accept_language;
+
+        assert_eq!(pick("en-US,en;q=0.9,ru;q=0.8"), Some(Language::En));
+        assert_eq!(pick("de, ru;q=0.5, en;q=0.4"), Some(Language::Ru));
+        assert_eq!(pick("en;q=0, ru;q=0.1"), Some(Language::Ru));
+        assert_eq!(pick("de"), None);
+    }
+}
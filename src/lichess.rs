@@ -1,21 +1,172 @@
-use reqwest::Url;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-/// Converts Lichess game URL to PGN export URL.
-pub fn game_url_to_export_url(url: &Url) -> Option<String> {
-    let domain = url.domain()?;
-    let mut game_id = url.path_segments()?.next()?;
+use anyhow::bail;
+use reqwest::{Client, Url};
+use tokio::sync::Semaphore;
+
+use crate::config::LichessConfig;
+use crate::metrics::Metrics;
+
+/// Optional annotations included in the exported PGN.
+#[derive(Default, Clone, Copy)]
+pub struct ExportOptions {
+    /// Include `[%clk]` comments with the remaining time after each move.
+    pub clocks: bool,
+
+    /// Include `[%eval]` comments from the server analysis, if the game was analysed.
+    pub evals: bool,
+}
+
+/// Host the games are downloaded from. Links to other hosts are rejected, so that the games
+/// cannot be served by the students themselves and the server does not make requests elsewhere.
+pub const HOST: &str = "lichess.org";
+
+/// Returns whether the URL points to Lichess over HTTPS.
+pub fn is_lichess_url(url: &Url) -> bool {
+    url.scheme() == "https" && url.host_str() == Some(HOST) && url.port().is_none()
+}
+
+/// Extracts game ID from Lichess game URL.
+pub fn game_id(url: &Url) -> Option<&str> {
+    let game_id = url.path_segments()?.next()?;
+
+    if game_id.is_empty() || !game_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
 
     // The long game ID can be provided. In this case, the first 8 characters is the actual ID, and
     // the last 4 is the presentation suffix. The suffix can be safely trimmed, which results in
     // the analysis board ID.
-    if game_id.len() == 12 {
-        game_id = &game_id[0..8];
+    match game_id.len() {
+        12 => game_id.get(0..8),
+        _ => Some(game_id),
+    }
+}
+
+/// Returns the canonical Lichess URL of the game, e.g. `https://lichess.org/q7ZvsdUF`.
+pub fn game_url(game_id: &str) -> String {
+    format!("https://{HOST}/{game_id}")
+}
+
+/// Converts Lichess game URL to PGN export URL. Returns `None` for the links to other hosts.
+pub fn game_url_to_export_url(url: &Url, options: ExportOptions) -> Option<String> {
+    if !is_lichess_url(url) {
+        return None;
     }
 
     Some(format!(
-        "{}://{}/game/export/{}?evals=0&clocks=0",
-        url.scheme(),
-        domain,
-        game_id
+        "https://{HOST}/game/export/{}?evals={}&clocks={}",
+        game_id(url)?,
+        options.evals as u8,
+        options.clocks as u8,
     ))
 }
+
+/// Client downloading the games from Lichess.
+pub struct Lichess {
+    client: Client,
+
+    /// Limits the number of concurrent downloads, so that Lichess does not block the server.
+    downloads: Semaphore,
+
+    metrics: Arc<Metrics>,
+}
+
+impl Lichess {
+    pub fn new(config: &LichessConfig, metrics: Arc<Metrics>) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent(&config.user_agent)
+            .build()?;
+
+        Ok(Self {
+            client,
+            downloads: Semaphore::new(config.max_concurrent_requests.max(1)),
+            metrics,
+        })
+    }
+
+    /// Downloads PGN of the game by its URL.
+    pub async fn fetch_pgn(
+        &self,
+        game_url: &str,
+        options: ExportOptions,
+    ) -> anyhow::Result<String> {
+        let url = Url::parse(game_url)?;
+        let export_url = match game_url_to_export_url(&url, options) {
+            Some(url) => url,
+            None => bail!("cannot extract game export URL"),
+        };
+
+        let _permit = self.downloads.acquire().await?;
+        let started = Instant::now();
+        let pgn = self.download(export_url).await;
+        self.metrics.record_fetch(started.elapsed());
+
+        pgn
+    }
+
+    async fn download(&self, url: String) -> anyhow::Result<String> {
+        let pgn = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(pgn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_url(url: &str) -> Option<String> {
+        game_url_to_export_url(&Url::parse(url).unwrap(), ExportOptions::default())
+    }
+
+    #[test]
+    fn extracts_game_ids() {
+        for url in [
+            "https://lichess.org/fFvoFPd1sAGr",
+            "https://lichess.org/fFvoFPd1",
+            "https://lichess.org/fFvoFPd1/black",
+        ] {
+            assert_eq!(
+                game_id(&Url::parse(url).unwrap()),
+                Some("fFvoFPd1"),
+                "{url}"
+            );
+        }
+
+        assert_eq!(game_id(&Url::parse("https://lichess.org/").unwrap()), None);
+        assert_eq!(
+            game_id(&Url::parse("https://lichess.org/%2e%2e").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn exports_only_from_lichess() {
+        assert_eq!(
+            export_url("https://lichess.org/fFvoFPd1sAGr"),
+            Some(String::from(
+                "https://lichess.org/game/export/fFvoFPd1?evals=0&clocks=0"
+            ))
+        );
+
+        for url in [
+            "https://example.com/fFvoFPd1",
+            "https://lichess.org.example.com/fFvoFPd1",
+            "http://lichess.org/fFvoFPd1",
+            "https://lichess.org:8443/fFvoFPd1",
+            "https://127.0.0.1/fFvoFPd1",
+        ] {
+            assert_eq!(export_url(url), None, "{url}");
+        }
+    }
+}
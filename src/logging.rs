@@ -0,0 +1,52 @@
+use std::io;
+
+use anyhow::anyhow;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderName,
+};
+use tracing::Span;
+use tracing_subscriber::{EnvFilter, fmt::format::FmtSpan};
+
+use crate::config::{LogConfig, LogFormat};
+
+/// Header with the ID of the request, generated unless sent by the client or a proxy.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Installs the global subscriber writing the events to stderr. Closed spans are logged with
+/// their durations.
+pub fn init(config: &LogConfig) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(&config.filter)?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr);
+
+    match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    }
+    .map_err(|e| anyhow!(e))
+}
+
+/// Creates the span of an HTTP request. The route is logged instead of the path, since the path
+/// and the query may contain personal data.
+pub fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        route,
+    )
+}
@@ -1,20 +1,108 @@
-use std::env;
+use std::net::SocketAddr;
+use std::process;
+use std::sync::Arc;
 use std::time::Duration;
 
 use axum::{
-    Json, Router,
+    Extension, Json, Router,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    http::{HeaderMap, header},
+    middleware,
+    response::IntoResponse,
     routing::{get, post},
 };
+use axum_extra::extract::{
+    CookieJar,
+    cookie::{Cookie, SameSite},
+};
+use clap::Parser;
 use reqwest::StatusCode;
+use serde::Deserialize;
 use tokio::signal;
-use tower_http::timeout::TimeoutLayer;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    timeout::TimeoutLayer,
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{Level, Span, instrument};
 
+mod archive;
+mod auth;
+mod cli;
+mod config;
+mod dashboard;
 mod data;
+mod engine;
+mod error;
+mod eval;
+mod form;
+mod i18n;
 mod lichess;
+mod logging;
+mod metrics;
+mod name;
+mod openapi;
+mod opening;
+mod pgn;
+mod rate_limit;
+mod result;
+mod rules;
+mod submissions;
+mod validation;
 mod xlsx;
 
+use crate::archive::{Archive, ArchivedReport, NewReport};
+use crate::auth::{Auth, CurrentUser, SESSION_COOKIE, SESSION_LIFETIME};
+use crate::cli::{Cli, Command};
+use crate::config::Config;
+use crate::dashboard::{Filter, SubmissionPage};
 use crate::data::Data;
-use crate::xlsx::{Report, XlsxResponse};
+use crate::engine::Engine;
+use crate::error::ApiError;
+use crate::form::ReportRequest;
+use crate::i18n::Language;
+use crate::lichess::Lichess;
+use crate::logging::REQUEST_ID_HEADER;
+use crate::metrics::{ErrorCause, Metrics, ReportTimer};
+use crate::rate_limit::RateLimiter;
+use crate::rules::RuleSets;
+use crate::submissions::SubmissionIndex;
+use crate::validation::Formats;
+use crate::xlsx::{Report, XlsxResponse, spreadsheet_to_bytes};
+
+#[derive(Clone)]
+struct AppState {
+    config: Arc<Config>,
+
+    /// Client downloading the submitted games.
+    lichess: Arc<Lichess>,
+
+    /// Local engine used for the analysis of the games, if configured.
+    engine: Option<Arc<Engine>>,
+
+    /// Rules the submitted games must comply with.
+    rules: Arc<RuleSets>,
+
+    /// Formats of the student and subject fields.
+    formats: Arc<Formats>,
+
+    /// Games submitted by the students so far.
+    submissions: Arc<SubmissionIndex>,
+
+    /// Archive of the generated reports, if configured.
+    archive: Option<Arc<Archive>>,
+
+    /// Users allowed to access the archive and the dashboard.
+    auth: Arc<Auth>,
+
+    metrics: Arc<Metrics>,
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    login: String,
+    password: String,
+}
 
 macro_rules! serve_static {
     ($path:literal, $content_type:expr) => {
@@ -30,41 +118,428 @@ macro_rules! serve_static {
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
+    let config = Config::load(&cli.config).unwrap_or_else(|e| {
+        eprintln!("Error: {e:#}");
+        process::exit(1);
+    });
+
+    if cli.config.print_config {
+        print!("{}", toml::to_string(&config).unwrap());
+        return;
+    }
+
+    if let Err(e) = logging::init(&config.log) {
+        eprintln!("Error: invalid log filter: {e:#}");
+        process::exit(1);
+    }
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config).await,
+        Command::Generate(args) => {
+            if let Err(e) = cli::generate(&config, *args).await {
+                eprintln!("Error: {e:#}");
+                process::exit(1);
+            }
+        }
+        Command::Healthcheck => {
+            if let Err(e) = cli::healthcheck(&config).await {
+                eprintln!("Error: {e:#}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+async fn serve(config: Config) {
+    let metrics = Arc::new(Metrics::default());
+
+    let state = AppState {
+        lichess: Arc::new(Lichess::new(&config.lichess, metrics.clone()).unwrap()),
+        engine: Engine::from_config(&config.engine).map(Arc::new),
+        rules: Arc::new(RuleSets::load(config.rules_path.as_deref()).unwrap()),
+        formats: Arc::new(Formats::load(config.formats_path.as_deref()).unwrap()),
+        submissions: Arc::new(SubmissionIndex::load(config.submissions_path.as_deref()).unwrap()),
+        archive: Archive::open(config.archive_path.as_deref())
+            .unwrap()
+            .map(Arc::new),
+        auth: Arc::new(Auth::load(config.users_path.as_deref()).unwrap()),
+        config: Arc::new(config),
+        metrics,
+    };
+
+    // Students can access their own archived reports, teachers can access all of them.
+    let user_routes = Router::new()
+        .route(
+            "/api/v1/students/{student_id}/reports",
+            get(list_archived_reports),
+        )
+        .route("/api/v1/reports/{id}", get(download_archived_report))
+        .route_layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            auth::require_user,
+        ));
+
+    // Every report downloads two games from Lichess, so the clients are limited to protect the
+    // server from being blocked there.
+    let mut report_routes = Router::new().route("/api/v1/report", post(generate_report));
+    if let Some(limiter) = RateLimiter::from_config(&state.config.rate_limit) {
+        report_routes = report_routes.route_layer(middleware::from_fn_with_state(
+            Arc::new(limiter),
+            rate_limit::limit,
+        ));
+    }
+
+    let teacher_routes = Router::new()
+        .route("/api/v1/submissions", get(list_submissions))
+        .route_layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            auth::require_teacher,
+        ));
+
     let app = Router::new()
         .route("/", get(serve_static!("index.html", "text/html")))
         .route("/style.css", get(serve_static!("style.css", "text/css")))
         .route("/app.js", get(serve_static!("app.js", "text/javascript")))
+        .route(
+            "/dashboard",
+            get(serve_static!("dashboard.html", "text/html")),
+        )
+        .route(
+            "/dashboard.js",
+            get(serve_static!("dashboard.js", "text/javascript")),
+        )
+        .route("/login", get(serve_static!("login.html", "text/html")))
+        .route(
+            "/login.js",
+            get(serve_static!("login.js", "text/javascript")),
+        )
         .route(
             "/favicon.png",
             get(serve_static!("favicon.png", "image/png")),
         )
-        .route("/api/v1/report", post(generate_report))
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/readyz", get(readiness))
+        .route("/metrics", get(export_metrics))
+        .route("/api/v1/openapi.json", get(openapi::serve))
+        .route("/api/v1/login", post(login))
+        .route("/api/v1/logout", post(logout))
+        .merge(report_routes)
+        .merge(user_routes)
+        .merge(teacher_routes)
         .layer(TimeoutLayer::with_status_code(
             StatusCode::REQUEST_TIMEOUT,
-            Duration::from_secs(15),
-        ));
-
-    let addr = env::var("LISTEN_ADDR").unwrap_or(String::from("0.0.0.0:8000"));
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            Duration::from_secs(state.config.request_timeout_secs),
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            metrics::track_requests,
+        ))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(logging::request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .with_state(state.clone());
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+    let listener = tokio::net::TcpListener::bind(&state.config.listen_addr)
         .await
         .unwrap();
+
+    tracing::info!(addr = %state.config.listen_addr, "listening");
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await
+    .unwrap();
+}
+
+/// Generates the report. Only the game IDs are logged, not the personal data of the student.
+#[utoipa::path(
+    post,
+    path = "/api/v1/report",
+    tag = "reports",
+    summary = "Generate a report",
+    description = "Downloads both games from Lichess, checks them and returns the XLSX report.",
+    request_body(content(
+        (Data = "application/json"),
+        (ReportRequest = "application/x-www-form-urlencoded"),
+        (ReportRequest = "multipart/form-data"),
+    )),
+    params(
+        ("Accept-Language" = Option<String>, Header,
+            description = "Language of the report and the errors if `options.language` is not set"),
+    ),
+    responses(
+        (status = 200, response = XlsxResponse),
+        (status = 400, description = "Invalid fields, games or options. The code is one of \
+            `validation_failed`, `duplicate_game`, `analysis_unavailable`, or `invalid_request` \
+            for malformed JSON", body = ApiError),
+        (status = 408, description = "The report was not generated in time"),
+        (status = 422, description = "The body does not match the schema, \
+            code `invalid_request`", body = ApiError),
+        (status = 429, description = "Too many requests from the client, code `rate_limited`",
+            body = ApiError,
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
+        (status = 500, description = "The analysis or the spreadsheet failed, code \
+            `analysis_failed` or `internal_error`", body = ApiError),
+    )
+)]
+#[instrument(skip_all, fields(white_game_id, black_game_id))]
+async fn generate_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Result<ReportRequest, ApiError>,
+) -> Result<XlsxResponse, ApiError> {
+    let timer = ReportTimer::start(state.metrics.clone());
+    let result = build_report(&state, &headers, request).await;
+    let duration_ms = timer.elapsed().as_millis() as u64;
+
+    match &result {
+        Ok(_) => {
+            timer.succeed();
+            tracing::info!(duration_ms, "report generated");
+        }
+        Err(e) => {
+            let cause = ErrorCause::of(e);
+            timer.fail(cause);
+            tracing::warn!(
+                duration_ms,
+                cause = cause.label(),
+                code = e.code,
+                fields = ?e.errors.iter().map(|field| field.code).collect::<Vec<_>>(),
+                "report failed"
+            );
+        }
+    }
+
+    result
 }
 
-async fn generate_report(Json(data): Json<Data>) -> Result<XlsxResponse, (StatusCode, String)> {
-    data.validate()
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+async fn build_report(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: Result<ReportRequest, ApiError>,
+) -> Result<XlsxResponse, ApiError> {
+    let ReportRequest { mut data, uploads } = request?;
+
+    let lang = *data
+        .options
+        .language
+        .get_or_insert_with(|| Language::from_headers(headers));
+
+    let uploads = data.parse_uploads(&uploads)?;
+    data.validate(&state.formats)?;
+
+    let (white_id, black_id) = data.game.game_ids().map_err(ApiError::internal)?;
+    Span::current()
+        .record("white_game_id", &white_id)
+        .record("black_game_id", &black_id);
+
+    let (mut game_white, mut game_black) = data
+        .load_games(&state.lichess, &state.rules, uploads)
+        .await?;
+
+    state
+        .submissions
+        .register(
+            &data.student.id,
+            (&white_id, &game_white),
+            (&black_id, &game_black),
+            lang,
+        )
+        .map_err(|e| ApiError::bad_request("duplicate_game", e.to_string()))?;
+
+    if data.options.analysis {
+        let engine = state.engine.as_ref().ok_or_else(|| {
+            ApiError::bad_request(
+                "analysis_unavailable",
+                lang.text("error.analysis_unavailable"),
+            )
+        })?;
 
-    let report = Report::new(data);
+        tokio::try_join!(
+            engine.analyse(&mut game_white),
+            engine.analyse(&mut game_black)
+        )
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "analysis_failed",
+                e.to_string(),
+            )
+        })?;
+    }
+
+    let report = Report::new(data, game_white, game_black, &state.config);
+    let filename = report.filename();
 
-    let spreadsheet = report
+    let bytes = report
         .generate_spreadsheet()
+        .and_then(|spreadsheet| spreadsheet_to_bytes(&spreadsheet))
+        .map_err(ApiError::internal)?;
+
+    if let Some(archive) = &state.archive {
+        let created_at = report.generation_time();
+        let (data, game_white, game_black) = report.into_parts();
+
+        archive
+            .store(NewReport {
+                created_at,
+                data,
+                white_game_id: white_id,
+                black_game_id: black_id,
+                game_white,
+                game_black,
+                filename: filename.clone(),
+                xlsx: bytes.clone(),
+            })
+            .await
+            .map_err(ApiError::internal)?;
+    }
+
+    Ok(XlsxResponse::new(filename, bytes))
+}
+
+async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    credentials: Result<Json<Credentials>, JsonRejection>,
+) -> Result<(CookieJar, Json<CurrentUser>), ApiError> {
+    let Json(credentials) = credentials?;
+
+    let token = state
+        .auth
+        .clone()
+        .login(credentials.login, credentials.password)
+        .await
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_credentials",
+                Language::from_headers(&headers).text("error.invalid_credentials"),
+            )
+        })?;
+
+    let user = state
+        .auth
+        .user(&token)
+        .ok_or_else(|| ApiError::internal("cannot start session"))?;
+
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(SESSION_LIFETIME.try_into().unwrap_or_default());
+
+    Ok((jar.add(cookie), Json(user)))
+}
+
+async fn logout(State(state): State<AppState>, jar: CookieJar) -> CookieJar {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        state.auth.logout(cookie.value());
+    }
+
+    jar.remove(Cookie::build(SESSION_COOKIE).path("/"))
+}
+
+async fn list_archived_reports(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Extension(user): Extension<CurrentUser>,
+    Path(student_id): Path<String>,
+) -> Result<Json<Vec<ArchivedReport>>, ApiError> {
+    let lang = Language::from_headers(&headers);
+
+    if !user.can_access_student(&student_id) {
+        return Err(ApiError::forbidden(lang));
+    }
+
+    let archive = state.archive.ok_or_else(|| archive_unavailable(lang))?;
+    let reports = archive.list(student_id).await.map_err(ApiError::internal)?;
+
+    Ok(Json(reports))
+}
+
+async fn download_archived_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i64>,
+) -> Result<XlsxResponse, ApiError> {
+    let lang = Language::from_headers(&headers);
+    let archive = state.archive.ok_or_else(|| archive_unavailable(lang))?;
+
+    let file = archive
+        .file(id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(ApiError::internal)?
+        .filter(|file| user.can_access_student(&file.student_id))
+        .ok_or_else(|| ApiError::not_found(lang.text("error.report_not_found")))?;
+
+    Ok(XlsxResponse::new(file.filename, file.xlsx))
+}
+
+async fn list_submissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(filter): Query<Filter>,
+) -> Result<Json<SubmissionPage>, ApiError> {
+    let lang = Language::from_headers(&headers);
+    let archive = state.archive.ok_or_else(|| archive_unavailable(lang))?;
+    let limit = filter.limit();
+
+    let reports = archive
+        .reports(filter.into_query())
+        .await
+        .map_err(ApiError::internal)?;
+
+    let game_ids = reports
+        .iter()
+        .flat_map(|report| [report.white_game_id.clone(), report.black_game_id.clone()])
+        .collect();
+    let claims = archive.claims(game_ids).await.map_err(ApiError::internal)?;
+
+    Ok(Json(dashboard::page(&reports, &claims, limit)))
+}
+
+/// Checks that the archive and the engine, if configured, are usable.
+async fn readiness(State(state): State<AppState>) -> (StatusCode, String) {
+    let archive = match &state.archive {
+        Some(archive) => Some(archive.check().await),
+        None => None,
+    };
+    let checks = [archive, state.engine.as_ref().map(|engine| engine.check())];
+    let errors: Vec<_> = checks
+        .into_iter()
+        .flatten()
+        .filter_map(Result::err)
+        .map(|e| format!("{e:#}"))
+        .collect();
+
+    if errors.is_empty() {
+        (StatusCode::OK, String::from("ok"))
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, errors.join("\n"))
+    }
+}
+
+async fn export_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
 
-    Ok(XlsxResponse::new(report.filename(), spreadsheet))
+fn archive_unavailable(lang: Language) -> ApiError {
+    ApiError::not_found(lang.text("error.archive_unavailable"))
 }
 
 async fn shutdown_signal() {
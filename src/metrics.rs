@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error::ApiError;
+
+/// Upper bounds of the latency histogram buckets in seconds.
+const REPORT_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 15.0];
+const FETCH_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Stage at which the generation of a report failed.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ErrorCause {
+    /// The request is malformed, or the data or the games do not pass the checks.
+    Validation,
+
+    /// The game cannot be downloaded from Lichess.
+    Fetch,
+
+    /// The downloaded game is not a valid PGN.
+    Parse,
+
+    /// The engine analysis failed.
+    Analysis,
+
+    /// The spreadsheet cannot be generated or stored in the archive.
+    Write,
+
+    /// The request was cancelled before the report was generated, usually by the request
+    /// timeout.
+    Timeout,
+}
+
+/// Metrics of the server exported in the Prometheus text format.
+pub struct Metrics {
+    requests: Mutex<BTreeMap<u16, u64>>,
+    report_errors: Mutex<BTreeMap<ErrorCause, u64>>,
+    report_duration: Histogram,
+    fetch_duration: Histogram,
+}
+
+struct Histogram {
+    buckets: &'static [f64],
+    state: Mutex<HistogramState>,
+}
+
+#[derive(Default)]
+struct HistogramState {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl ErrorCause {
+    /// Determines the failed stage from the error returned to the user.
+    pub fn of(error: &ApiError) -> Self {
+        let codes = || error.errors.iter().map(|field| field.code);
+
+        if codes().any(|code| code == "game_unavailable") {
+            Self::Fetch
+        } else if codes().any(|code| code == "invalid_game") {
+            Self::Parse
+        } else {
+            match error.code {
+                "analysis_failed" => Self::Analysis,
+                "internal_error" => Self::Write,
+                _ => Self::Validation,
+            }
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Validation => "validation",
+            Self::Fetch => "fetch",
+            Self::Parse => "parse",
+            Self::Analysis => "analysis",
+            Self::Write => "write",
+            Self::Timeout => "timeout",
+        }
+    }
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            state: Mutex::new(HistogramState {
+                counts: vec![0; buckets.len()],
+                ..HistogramState::default()
+            }),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut state = self.state.lock().unwrap();
+
+        for (bound, count) in self.buckets.iter().zip(&mut state.counts) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        state.sum += seconds;
+        state.count += 1;
+    }
+
+    fn render(&self, output: &mut String, name: &str, help: &str) {
+        let state = self.state.lock().unwrap();
+
+        writeln!(output, "# HELP {name} {help}").unwrap();
+        writeln!(output, "# TYPE {name} histogram").unwrap();
+        for (bound, count) in self.buckets.iter().zip(&state.counts) {
+            writeln!(output, "{name}_bucket{{le=\"{bound}\"}} {count}").unwrap();
+        }
+        writeln!(output, "{name}_bucket{{le=\"+Inf\"}} {}", state.count).unwrap();
+        writeln!(output, "{name}_sum {}", state.sum).unwrap();
+        writeln!(output, "{name}_count {}", state.count).unwrap();
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            requests: Mutex::default(),
+            report_errors: Mutex::default(),
+            report_duration: Histogram::new(REPORT_BUCKETS),
+            fetch_duration: Histogram::new(FETCH_BUCKETS),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn record_request(&self, status: StatusCode) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(status.as_u16())
+            .or_default() += 1;
+    }
+
+    /// Records the time of a successfully generated report.
+    pub fn record_report(&self, duration: Duration) {
+        self.report_duration.observe(duration);
+    }
+
+    pub fn record_report_error(&self, cause: ErrorCause) {
+        *self.report_errors.lock().unwrap().entry(cause).or_default() += 1;
+    }
+
+    /// Records the time of a game download from Lichess, including the failed ones.
+    pub fn record_fetch(&self, duration: Duration) {
+        self.fetch_duration.observe(duration);
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str("# HELP http_requests_total Number of handled HTTP requests.\n");
+        output.push_str("# TYPE http_requests_total counter\n");
+        for (status, count) in self.requests.lock().unwrap().iter() {
+            writeln!(output, "http_requests_total{{status=\"{status}\"}} {count}").unwrap();
+        }
+
+        self.report_duration.render(
+            &mut output,
+            "report_generation_duration_seconds",
+            "Time of generating a report.",
+        );
+        self.fetch_duration.render(
+            &mut output,
+            "lichess_fetch_duration_seconds",
+            "Time of downloading a game from Lichess.",
+        );
+
+        output.push_str("# HELP report_errors_total Number of failed reports by cause.\n");
+        output.push_str("# TYPE report_errors_total counter\n");
+        for (cause, count) in self.report_errors.lock().unwrap().iter() {
+            writeln!(
+                output,
+                "report_errors_total{{cause=\"{}\"}} {count}",
+                cause.label()
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
+
+/// Generation of a report in progress. The timeout layer cancels the handler by dropping it, so
+/// a report dropped before its outcome is recorded counts as [`ErrorCause::Timeout`].
+pub struct ReportTimer {
+    metrics: Arc<Metrics>,
+    started: Instant,
+    finished: bool,
+}
+
+impl ReportTimer {
+    pub fn start(metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Records the time of the generated report.
+    pub fn succeed(mut self) {
+        self.finished = true;
+        self.metrics.record_report(self.elapsed());
+    }
+
+    pub fn fail(mut self, cause: ErrorCause) {
+        self.finished = true;
+        self.metrics.record_report_error(cause);
+    }
+}
+
+impl Drop for ReportTimer {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.metrics.record_report_error(ErrorCause::Timeout);
+        }
+    }
+}
+
+/// Middleware counting the responses by status.
+pub async fn track_requests(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    metrics.record_request(response.status());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_cancelled_report_as_timeout() {
+        let metrics = Arc::new(Metrics::default());
+
+        ReportTimer::start(metrics.clone()).succeed();
+        ReportTimer::start(metrics.clone()).fail(ErrorCause::Fetch);
+        drop(ReportTimer::start(metrics.clone()));
+
+        let output = metrics.render();
+        assert!(output.contains("report_generation_duration_seconds_count 1\n"));
+        assert!(output.contains("report_errors_total{cause=\"fetch\"} 1\n"));
+        assert!(output.contains("report_errors_total{cause=\"timeout\"} 1\n"));
+    }
+}
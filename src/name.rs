@@ -0,0 +1,187 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+/// Full name of a person split into parts.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct PersonName {
+    pub surname: String,
+    pub given_name: String,
+
+    /// Patronymic, which foreign students usually do not have.
+    pub patronymic: Option<String>,
+}
+
+/// Format of the player names in the game tables.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NameFormat {
+    /// `Иванов Иван`
+    #[default]
+    SurnameGivenName,
+
+    /// `Иванов И.И.`
+    SurnameInitials,
+
+    /// `Иванов Иван Иванович`
+    Full,
+}
+
+impl PersonName {
+    /// Splits the name written as `Surname Given-name Patronymic`. A single word is treated as
+    /// the surname, and all words after the given name as the patronymic. Double surnames written
+    /// with a space can only be passed in the explicit fields of the request.
+    pub fn parse(name: &str) -> Self {
+        let mut words = name.split_whitespace();
+        let surname = words.next().unwrap_or_default();
+        let given_name = words.next().unwrap_or_default();
+        let patronymic = words.collect::<Vec<_>>().join(" ");
+
+        Self {
+            surname: String::from(surname),
+            given_name: String::from(given_name),
+            patronymic: (!patronymic.is_empty()).then_some(patronymic),
+        }
+    }
+
+    pub fn format(&self, format: NameFormat) -> String {
+        let parts = match format {
+            NameFormat::SurnameGivenName => vec![self.surname.clone(), self.given_name.clone()],
+            NameFormat::SurnameInitials => {
+                let initials: String = [Some(&self.given_name), self.patronymic.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .map(|name| initials(name))
+                    .collect();
+
+                vec![self.surname.clone(), initials]
+            }
+            NameFormat::Full => vec![
+                self.surname.clone(),
+                self.given_name.clone(),
+                self.patronymic.clone().unwrap_or_default(),
+            ],
+        };
+
+        parts
+            .iter()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl FromStr for NameFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "surname_given_name" => Ok(Self::SurnameGivenName),
+            "surname_initials" => Ok(Self::SurnameInitials),
+            "full" => Ok(Self::Full),
+            format => bail!("unknown name format {format}"),
+        }
+    }
+}
+
+/// Returns initials of the name, e.g. `И.` for `Иван` or `А.-М.` for `Анна-Мария`.
+fn initials(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            word.split('-')
+                .filter_map(|part| part.chars().next())
+                .map(|letter| format!("{}.", letter.to_uppercase()))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Data, StudentData};
+    use crate::validation::Formats;
+
+    fn name(surname: &str, given_name: &str, patronymic: Option<&str>) -> PersonName {
+        PersonName {
+            surname: String::from(surname),
+            given_name: String::from(given_name),
+            patronymic: patronymic.map(String::from),
+        }
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(
+            PersonName::parse("  Иванов  Иван Иванович "),
+            name("Иванов", "Иван", Some("Иванович"))
+        );
+        assert_eq!(PersonName::parse("Smith John"), name("Smith", "John", None));
+        assert_eq!(PersonName::parse("Иванов"), name("Иванов", "", None));
+        assert_eq!(
+            PersonName::parse("Алиев Рашид Рамиз оглы"),
+            name("Алиев", "Рашид", Some("Рамиз оглы"))
+        );
+    }
+
+    #[test]
+    fn formats_names() {
+        let full = name("Иванов", "Анна-Мария", Some("Ивановна"));
+        assert_eq!(
+            full.format(NameFormat::SurnameGivenName),
+            "Иванов Анна-Мария"
+        );
+        assert_eq!(full.format(NameFormat::SurnameInitials), "Иванов А.-М.И.");
+        assert_eq!(full.format(NameFormat::Full), "Иванов Анна-Мария Ивановна");
+
+        let foreign = name("Smith", "john", None);
+        assert_eq!(foreign.format(NameFormat::SurnameInitials), "Smith J.");
+        assert_eq!(foreign.format(NameFormat::Full), "Smith john");
+        assert_eq!(name("Иванов", "", None).format(NameFormat::Full), "Иванов");
+    }
+
+    #[test]
+    fn parses_name_formats() {
+        assert_eq!("full".parse::<NameFormat>().unwrap(), NameFormat::Full);
+        assert!("initials".parse::<NameFormat>().is_err());
+    }
+
+    #[test]
+    fn takes_explicit_name_parts() {
+        let mut student = StudentData {
+            name: String::from("Петров Водкин Кузьма Сергеевич"),
+            ..StudentData::default()
+        };
+        assert_eq!(
+            student.person_name(),
+            name("Петров", "Водкин", Some("Кузьма Сергеевич"))
+        );
+
+        student.surname = Some(String::from(" Петров-Водкин "));
+        student.given_name = Some(String::from("Кузьма"));
+        student.patronymic = Some(String::from("Сергеевич"));
+        assert_eq!(
+            student.person_name(),
+            name("Петров-Водкин", "Кузьма", Some("Сергеевич"))
+        );
+
+        student.patronymic = Some(String::from("  "));
+        assert_eq!(student.person_name(), name("Петров-Водкин", "Кузьма", None));
+    }
+
+    #[test]
+    fn requires_surname_and_given_name_with_explicit_parts() {
+        let mut data = Data::default();
+        data.student.name = String::from("Петров Водкин Кузьма");
+        data.student.patronymic = Some(String::from("Сергеевич"));
+
+        let error = data.validate(&Formats::default()).err().unwrap();
+        let fields: Vec<_> = error.errors.iter().map(|error| error.field).collect();
+
+        assert!(fields.contains(&"student.surname"));
+        assert!(fields.contains(&"student.given_name"));
+    }
+}
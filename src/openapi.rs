@@ -0,0 +1,116 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+use crate::xlsx::XlsxResponse;
+
+/// OpenAPI document of the report API. The schemas are derived from the request and error
+/// types, so they follow the changes of the code.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Lichess XLSX Reports",
+        description = "Generates XLSX reports on chess games played on Lichess.",
+        license(name = "Unlicense", identifier = "Unlicense")
+    ),
+    paths(crate::generate_report),
+    components(responses(XlsxResponse)),
+    tags((name = "reports", description = "Report generation"))
+)]
+pub struct ApiDoc;
+
+pub async fn serve() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::data::Data;
+
+    const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    fn spec() -> Value {
+        serde_json::to_value(ApiDoc::openapi()).unwrap()
+    }
+
+    /// The committed `openapi.json` is the contract with the clients, so any change of the API
+    /// must be reviewed there. Run with `UPDATE_OPENAPI=1` to regenerate it.
+    #[test]
+    fn spec_matches_committed_file() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+        if env::var_os("UPDATE_OPENAPI").is_some() {
+            fs::write(SPEC_PATH, &generated).unwrap();
+            return;
+        }
+
+        let committed = fs::read_to_string(SPEC_PATH).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "openapi.json is out of date, run `UPDATE_OPENAPI=1 cargo test` and review the diff"
+        );
+    }
+
+    /// Every field the server serializes must be declared in the schema, and the schema must not
+    /// declare fields the server does not know.
+    #[test]
+    fn request_schema_matches_serialization() {
+        let spec = spec();
+        let mut data = Data::default();
+        data.student.surname = Some(String::from("Иванов"));
+        data.student.given_name = Some(String::from("Иван"));
+        data.student.patronymic = Some(String::from("Иванович"));
+        data.options.language = Some(Default::default());
+
+        assert_same_fields(&spec, "Data", &serde_json::to_value(&data).unwrap());
+    }
+
+    #[test]
+    fn report_endpoint_is_documented() {
+        let spec = spec();
+        let operation = &spec["paths"]["/api/v1/report"]["post"];
+
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Data"
+        );
+        for status in ["200", "400", "429", "500"] {
+            assert!(
+                operation["responses"][status].is_object(),
+                "response {status} is not documented"
+            );
+        }
+        assert!(operation["responses"]["429"]["headers"]["Retry-After"].is_object());
+    }
+
+    fn assert_same_fields(spec: &Value, schema: &str, value: &Value) {
+        let properties = spec["components"]["schemas"][schema]["properties"]
+            .as_object()
+            .unwrap_or_else(|| panic!("schema {schema} has no properties"));
+        let fields = value.as_object().unwrap();
+
+        let mut declared: Vec<_> = properties.keys().collect();
+        let mut serialized: Vec<_> = fields.keys().collect();
+        declared.sort();
+        serialized.sort();
+        assert_eq!(declared, serialized, "fields of {schema} differ");
+
+        for (name, field) in fields {
+            let reference = properties[name]["$ref"].as_str().or_else(|| {
+                properties[name]["oneOf"]
+                    .as_array()?
+                    .iter()
+                    .find_map(|variant| variant["$ref"].as_str())
+            });
+
+            if let (Some(reference), Value::Object(_)) = (reference, field) {
+                let nested = reference.trim_start_matches("#/components/schemas/");
+                assert_same_fields(spec, nested, field);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::LazyLock;
+
+use crate::pgn::Game;
+
+/// Bundled ECO database in the Lichess `chess-openings` format: code, name and moves.
+static ECO_DATABASE: LazyLock<Vec<Entry>> = LazyLock::new(|| {
+    include_str!("../static/eco.tsv")
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut columns = line.split('\t');
+            let eco = columns.next()?;
+            let name = columns.next()?;
+            let moves = columns
+                .next()?
+                .split_whitespace()
+                .filter(|token| !token.ends_with('.'))
+                .map(String::from)
+                .collect();
+
+            Some(Entry {
+                opening: Opening {
+                    eco: String::from(eco),
+                    name: String::from(name),
+                },
+                moves,
+            })
+        })
+        .collect()
+});
+
+struct Entry {
+    opening: Opening,
+    moves: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+}
+
+impl Opening {
+    /// Identifies the opening from the `ECO` and `Opening` PGN headers. If they are missing, the
+    /// longest line from the bundled ECO database that the game starts with is used instead.
+    pub fn of(game: &Game) -> Option<Self> {
+        let known = |header| game.header(header).filter(|value| *value != "?");
+
+        if let (Some(eco), Some(name)) = (known("ECO"), known("Opening")) {
+            return Some(Self {
+                eco: String::from(eco),
+                name: String::from(name),
+            });
+        }
+
+        // Openings from the database assume the standard starting position.
+        if game.header("FEN").is_some() {
+            return None;
+        }
+
+        ECO_DATABASE
+            .iter()
+            .filter(|entry| {
+                entry.moves.len() <= game.moves.len()
+                    && entry
+                        .moves
+                        .iter()
+                        .zip(&game.moves)
+                        .all(|(san, m)| *san == m.san)
+            })
+            .max_by_key(|entry| entry.moves.len())
+            .map(|entry| entry.opening.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Language;
+    use crate::pgn;
+
+    fn opening(pgn: &str) -> Option<(String, String)> {
+        let game = pgn::parse(pgn, Language::En).unwrap();
+        Opening::of(&game).map(|opening| (opening.eco, opening.name))
+    }
+
+    fn known(eco: &str, name: &str) -> Option<(String, String)> {
+        Some((String::from(eco), String::from(name)))
+    }
+
+    #[test]
+    fn takes_opening_from_headers() {
+        let pgn = "[ECO \"C65\"]\n[Opening \"Ruy Lopez: Berlin Defense\"]\n\n1. e4 e5 *";
+        assert_eq!(opening(pgn), known("C65", "Ruy Lopez: Berlin Defense"));
+    }
+
+    #[test]
+    fn matches_longest_line() {
+        assert_eq!(
+            opening("1. e4 e5 2. Nf3 Nc6 3. Bb5 h6 4. O-O *"),
+            known("C60", "Ruy Lopez")
+        );
+        assert_eq!(
+            opening("1. e4 e5 2. Qh5 Nc6 *"),
+            known("C20", "King's Pawn Game: Wayward Queen Attack")
+        );
+        assert_eq!(opening("1. e4 *"), known("B00", "King's Pawn Game"));
+    }
+
+    #[test]
+    fn falls_back_to_moves_for_unknown_headers() {
+        let pgn = "[ECO \"?\"]\n[Opening \"?\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+        assert_eq!(
+            opening(pgn),
+            known("C44", "King's Knight Opening: Normal Variation")
+        );
+    }
+
+    #[test]
+    fn skips_games_from_position() {
+        let pgn = "[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 *";
+        assert_eq!(opening(pgn), None);
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use pgn_reader::{RawComment, RawTag, Reader, SanPlus, Visitor};
+use shakmaty::{
+    CastlingMode, Color, Position, Role,
+    fen::Fen,
+    san::{SanError, Suffix},
+    variant::{Variant, VariantPosition},
+};
+
+use crate::eval::Eval;
+use crate::i18n::Language;
+
+/// Chess game parsed from PGN and replayed from the starting position.
+pub struct Game {
+    /// PGN the game was parsed from.
+    pub pgn: String,
+    pub headers: HashMap<String, String>,
+    pub moves: Vec<Move>,
+}
+
+/// Headers the rules, the report and the dashboard rely on, which an uploaded game must share
+/// with the game on Lichess.
+const VERIFIED_HEADERS: &[&str] = &[
+    "White",
+    "Black",
+    "Result",
+    "Variant",
+    "FEN",
+    "TimeControl",
+    "WhiteRatingDiff",
+    "BlackRatingDiff",
+    "Termination",
+];
+
+/// Single half-move (ply) of the game.
+pub struct Move {
+    /// Move in standard algebraic notation. Check and checkmate markers are recalculated during
+    /// the replay, so they are always present and correct.
+    pub san: String,
+
+    /// Move in long algebraic notation, e.g. `Ng1-f3` or `e7xd8=Q+`.
+    pub lan: String,
+
+    /// Move in UCI notation, e.g. `g1f3`, as expected by chess engines.
+    pub uci: String,
+
+    /// Side that made the move. Games set up from a position may start with Black's move.
+    pub color: Color,
+
+    /// Number of the full move, which may start from any number in games set up from a position.
+    pub number: u32,
+
+    /// Remaining time on the player's clock after the move, from the `[%clk]` annotation.
+    pub clock: Option<Duration>,
+
+    /// Evaluation of the position after the move, from the `[%eval]` annotation.
+    pub eval: Option<Eval>,
+}
+
+impl Game {
+    /// Returns value of the PGN header, if present.
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers.get(key).map(String::as_str)
+    }
+
+    /// Returns initial time and increment from the `TimeControl` header, e.g. `300+3`.
+    pub fn time_control(&self) -> Option<(Duration, Duration)> {
+        let (initial, increment) = self.header("TimeControl")?.split_once('+')?;
+
+        Some((
+            Duration::from_secs(initial.parse().ok()?),
+            Duration::from_secs(increment.parse().ok()?),
+        ))
+    }
+
+    /// Returns number of the first full move and index of the first half-move in it: `1` if the
+    /// game starts with Black's move.
+    pub fn first_move(&self) -> (u32, usize) {
+        match self.moves.first() {
+            Some(m) => (m.number, (m.color == Color::Black) as usize),
+            None => (1, 0),
+        }
+    }
+
+    /// Returns whether the game was rated. Lichess writes the rating changes of the players only
+    /// for the rated games, whatever the event is, e.g. in arena and swiss tournaments.
+    pub fn is_rated(&self) -> bool {
+        self.header("WhiteRatingDiff").is_some() || self.header("BlackRatingDiff").is_some()
+    }
+
+    /// Returns whether the game has the same moves and [`VERIFIED_HEADERS`] as the original one.
+    /// The annotations, e.g. the clocks and the evaluations, may differ.
+    pub fn matches(&self, original: &Game) -> bool {
+        VERIFIED_HEADERS
+            .iter()
+            .all(|&key| self.header(key) == original.header(key))
+            && self.moves.len() == original.moves.len()
+            && self
+                .moves
+                .iter()
+                .zip(&original.moves)
+                .all(|(a, b)| a.uci == b.uci)
+    }
+
+    /// Returns time the player spent on the half-move with the given index, counting from 0.
+    pub fn time_spent(&self, ply: usize) -> Option<Duration> {
+        let (initial, increment) = self.time_control()?;
+        let clock = self.moves.get(ply)?.clock?;
+        let previous = match ply.checked_sub(2) {
+            Some(previous) => self.moves[previous].clock?,
+            None => initial,
+        };
+
+        Some((previous + increment).saturating_sub(clock))
+    }
+}
+
+/// Parses PGN and replays the mainline with the rules of the game's variant.
+///
+/// Fails if any move is illegal or ambiguous. The error contains the number of the offending
+/// half-move, counting from 1.
+pub fn parse(pgn: &str, lang: Language) -> anyhow::Result<Game> {
+    let raw = match Reader::new(pgn.as_bytes()).read_game(&mut RawGameVisitor)? {
+        Some(raw) => raw,
+        None => bail!(lang.text("pgn.no_game")),
+    };
+
+    let (mut position, castling_mode) = raw.starting_position(lang)?;
+    let mut moves = Vec::with_capacity(raw.moves.len());
+
+    for (
+        i,
+        RawMove {
+            san: san_plus,
+            comment,
+        },
+    ) in raw.moves.into_iter().enumerate()
+    {
+        let ply = i + 1;
+
+        let m = match san_plus.san.to_move(&position) {
+            Ok(m) => m,
+            Err(SanError::IllegalSan) => {
+                bail!(lang.format("pgn.illegal_move", &[("san", &san_plus), ("ply", &ply)]))
+            }
+            Err(SanError::AmbiguousSan) => {
+                bail!(lang.format("pgn.ambiguous_move", &[("san", &san_plus), ("ply", &ply)]))
+            }
+        };
+
+        let color = position.turn();
+        let number = position.fullmoves().get();
+        let san = SanPlus::from_move_and_play_unchecked(&mut position, m);
+
+        moves.push(Move {
+            san: san.to_string(),
+            lan: long_algebraic(m, san.suffix),
+            uci: m.to_uci(castling_mode).to_string(),
+            color,
+            number,
+            clock: command(&comment, "clk").and_then(parse_clock),
+            eval: command(&comment, "eval").and_then(Eval::parse),
+        });
+    }
+
+    Ok(Game {
+        pgn: String::from(pgn),
+        headers: raw.headers,
+        moves,
+    })
+}
+
+fn long_algebraic(m: shakmaty::Move, suffix: Option<Suffix>) -> String {
+    let mut lan = match m {
+        shakmaty::Move::Castle { king, rook } if rook < king => String::from("O-O-O"),
+        shakmaty::Move::Castle { .. } => String::from("O-O"),
+        shakmaty::Move::Put { role, to } => format!("{}@{to}", role.upper_char()),
+        shakmaty::Move::Normal { from, to, .. } | shakmaty::Move::EnPassant { from, to } => {
+            let piece = match m.role() {
+                Role::Pawn => String::new(),
+                role => role.upper_char().to_string(),
+            };
+            let separator = if m.is_capture() { 'x' } else { '-' };
+
+            format!("{piece}{from}{separator}{to}")
+        }
+    };
+
+    if let Some(promotion) = m.promotion() {
+        lan.push('=');
+        lan.push(promotion.upper_char());
+    }
+
+    if let Some(suffix) = suffix {
+        lan.push(suffix.char());
+    }
+
+    lan
+}
+
+/// Returns argument of the embedded command in the comment, e.g. `0:03:00` for `[%clk 0:03:00]`.
+fn command<'a>(comment: &'a str, name: &str) -> Option<&'a str> {
+    let start = comment.find(&format!("[%{name} "))? + name.len() + 3;
+    let end = start + comment[start..].find(']')?;
+
+    Some(comment[start..end].trim())
+}
+
+/// Parses clock in `h:mm:ss` format. Fractions of a second are allowed. Returns `None` for
+/// malformed, negative or out of range values.
+fn parse_clock(clock: &str) -> Option<Duration> {
+    let mut parts = clock.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let whole = hours
+        .checked_mul(3600)?
+        .checked_add(minutes.checked_mul(60)?)?;
+    Duration::from_secs(whole).checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
+
+/// Game as read from PGN, before the moves are checked.
+struct RawGame {
+    headers: HashMap<String, String>,
+    moves: Vec<RawMove>,
+}
+
+struct RawMove {
+    san: SanPlus,
+    comment: String,
+}
+
+impl RawGame {
+    fn starting_position(&self, lang: Language) -> anyhow::Result<(VariantPosition, CastlingMode)> {
+        let variant_name = self
+            .headers
+            .get("Variant")
+            .map_or("Standard", String::as_str);
+        let variant = Variant::from_ascii(variant_name.as_bytes()).map_err(|_| {
+            anyhow!(lang.format("pgn.unknown_variant", &[("variant", &variant_name)]))
+        })?;
+
+        let castling_mode = match variant_name {
+            "Chess960" => CastlingMode::Chess960,
+            _ => CastlingMode::Standard,
+        };
+
+        let Some(fen) = self.headers.get("FEN") else {
+            return Ok((VariantPosition::new(variant), castling_mode));
+        };
+
+        let setup = Fen::from_ascii(fen.as_bytes())
+            .map_err(|_| anyhow!(lang.format("pgn.invalid_fen", &[("fen", fen)])))?
+            .into_setup();
+
+        let position = VariantPosition::from_setup(variant, setup, castling_mode)
+            .map_err(|_| anyhow!(lang.format("pgn.illegal_position", &[("fen", fen)])))?;
+
+        Ok((position, castling_mode))
+    }
+}
+
+struct RawGameVisitor;
+
+impl Visitor for RawGameVisitor {
+    type Tags = HashMap<String, String>;
+    type Movetext = RawGame;
+    type Output = RawGame;
+
+    fn begin_tags(&mut self) -> ControlFlow<Self::Output, Self::Tags> {
+        ControlFlow::Continue(HashMap::new())
+    }
+
+    fn tag(
+        &mut self,
+        tags: &mut Self::Tags,
+        name: &[u8],
+        value: RawTag<'_>,
+    ) -> ControlFlow<Self::Output> {
+        tags.insert(
+            String::from_utf8_lossy(name).into_owned(),
+            value.decode_utf8_lossy().into_owned(),
+        );
+        ControlFlow::Continue(())
+    }
+
+    fn begin_movetext(&mut self, tags: Self::Tags) -> ControlFlow<Self::Output, Self::Movetext> {
+        ControlFlow::Continue(RawGame {
+            headers: tags,
+            moves: Vec::with_capacity(120),
+        })
+    }
+
+    fn san(
+        &mut self,
+        movetext: &mut Self::Movetext,
+        san_plus: SanPlus,
+    ) -> ControlFlow<Self::Output> {
+        movetext.moves.push(RawMove {
+            san: san_plus,
+            comment: String::new(),
+        });
+        ControlFlow::Continue(())
+    }
+
+    fn comment(
+        &mut self,
+        movetext: &mut Self::Movetext,
+        comment: RawComment<'_>,
+    ) -> ControlFlow<Self::Output> {
+        if let Some(last) = movetext.moves.last_mut() {
+            last.comment
+                .push_str(&String::from_utf8_lossy(comment.as_bytes()));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn end_game(&mut self, movetext: Self::Movetext) -> Self::Output {
+        movetext
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r#"[Event "Rated blitz game"]
+[Site "https://lichess.org/abcdefgh"]
+[TimeControl "180+2"]
+
+1. e4 { [%eval 0.2] [%clk 0:03:00] } 1... e5 { [%eval 0.25] [%clk 0:03:01.5] }
+2. Nf3 { [%eval #3] [%clk 0:02:55] } 2... Nc6 { [%clk 0:02:50] } 3. Bb5 a6 4. Bxc6 dxc6
+5. O-O f6 6. d4 exd4 7. Nxd4 c5 8. Nb3 Qxd1 9. Rxd1 1-0"#;
+
+    fn parse(pgn: &str) -> anyhow::Result<Game> {
+        super::parse(pgn, Language::En)
+    }
+
+    #[test]
+    fn parses_game() {
+        let game = parse(GAME).unwrap();
+
+        assert_eq!(game.header("Site"), Some("https://lichess.org/abcdefgh"));
+        assert_eq!(game.moves.len(), 17);
+        assert_eq!(game.moves[0].san, "e4");
+        assert_eq!(game.moves[0].lan, "e2-e4");
+        assert_eq!(game.moves[2].uci, "g1f3");
+        assert_eq!(game.moves[6].lan, "Bb5xc6");
+        assert_eq!(game.moves[8].san, "O-O");
+        assert_eq!(game.moves[8].uci, "e1g1");
+        assert_eq!(game.moves[15].san, "Qxd1");
+        assert_eq!(game.moves[15].lan, "Qd8xd1");
+    }
+
+    #[test]
+    fn parses_annotations() {
+        let game = parse(GAME).unwrap();
+
+        assert_eq!(game.moves[0].clock, Some(Duration::from_secs(180)));
+        assert_eq!(game.moves[1].clock, Some(Duration::from_millis(181_500)));
+        assert!(game.moves[0].eval.is_some());
+        assert!(game.moves[2].eval.is_some());
+        assert!(game.moves[3].eval.is_none());
+        assert!(game.moves[4].clock.is_none());
+    }
+
+    #[test]
+    fn calculates_time_spent() {
+        let game = parse(GAME).unwrap();
+
+        assert_eq!(
+            game.time_control(),
+            Some((Duration::from_secs(180), Duration::from_secs(2)))
+        );
+        assert_eq!(game.time_spent(0), Some(Duration::from_secs(2)));
+        assert_eq!(game.time_spent(2), Some(Duration::from_secs(7)));
+        assert_eq!(game.time_spent(3), Some(Duration::from_millis(13_500)));
+        assert_eq!(game.time_spent(4), None);
+    }
+
+    #[test]
+    fn parses_clocks() {
+        assert_eq!(parse_clock("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_clock("0:00:00.25"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_clock("0:00:-1"), None);
+        assert_eq!(parse_clock("0:00:NaN"), None);
+        assert_eq!(parse_clock("0:00:inf"), None);
+        assert_eq!(parse_clock("0:00:1e30"), None);
+        assert_eq!(parse_clock("18446744073709551615:00:00"), None);
+        assert_eq!(parse_clock("0:00"), None);
+        assert_eq!(parse_clock("0:00:00:00"), None);
+    }
+
+    #[test]
+    fn starts_from_fen() {
+        let pgn = r#"[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+[SetUp "1"]
+
+1... Kd7 2. e4 *"#;
+        let game = parse(pgn).unwrap();
+
+        assert_eq!(game.moves[0].lan, "Ke8-d7");
+        assert_eq!(game.moves[0].color, Color::Black);
+        assert_eq!(game.moves[1].uci, "e2e4");
+        assert_eq!(game.moves[1].number, 2);
+        assert_eq!(game.first_move(), (1, 1));
+        assert_eq!(parse(GAME).unwrap().first_move(), (1, 0));
+    }
+
+    #[test]
+    fn replays_variants() {
+        let pgn = "[Variant \"Crazyhouse\"]\n\n1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. P@d4 *";
+        let game = parse(pgn).unwrap();
+
+        assert_eq!(game.moves[6].lan, "P@d4");
+
+        let error = parse("[Variant \"Chaturanga\"]\n\n1. e4 *").err().unwrap();
+        assert!(error.to_string().contains("Chaturanga"));
+    }
+
+    #[test]
+    fn rejects_invalid_games() {
+        assert!(parse("").is_err());
+        assert!(parse("1. e4 e5 2. Ke3 *").is_err());
+        assert!(parse("[FEN \"invalid\"]\n\n1. e4 *").is_err());
+
+        let ambiguous = r#"[FEN "4k3/8/8/8/8/8/8/2N1K1N1 w - - 0 1"]
+
+1. Ne2 *"#;
+        assert!(parse(ambiguous).is_err());
+    }
+
+    #[test]
+    fn matches_original_game() {
+        let original = parse(GAME).unwrap();
+
+        let without_annotations = GAME.replace(" { [%eval 0.2] [%clk 0:03:00] }", "");
+        assert!(parse(&without_annotations).unwrap().matches(&original));
+
+        let other_moves = GAME.replace("8. Nb3", "8. Nf3");
+        assert!(!parse(&other_moves).unwrap().matches(&original));
+
+        let shorter = GAME.replace(" 9. Rxd1", "");
+        assert!(!parse(&shorter).unwrap().matches(&original));
+
+        let other_time_control = GAME.replace("180+2", "600+0");
+        assert!(!parse(&other_time_control).unwrap().matches(&original));
+
+        let rated = GAME.replace("[TimeControl", "[WhiteRatingDiff \"+8\"]\n[TimeControl");
+        assert!(!parse(&rated).unwrap().matches(&original));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::RateLimitConfig;
+use crate::error::ApiError;
+use crate::i18n::Language;
+
+/// Number of tracked clients after which the ones with full buckets are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket rate limiter keyed by the client IP address.
+pub struct RateLimiter {
+    /// Maximum number of requests made at once.
+    burst: f64,
+
+    /// Tokens added per second.
+    rate: f64,
+
+    /// Take the client address from `X-Forwarded-For` appended by a reverse proxy.
+    trust_forwarded_for: bool,
+
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    /// Configures the limiter. Returns `None` if the rate limiting is disabled.
+    pub fn from_config(config: &RateLimitConfig) -> Option<Self> {
+        if config.requests_per_minute == 0 {
+            return None;
+        }
+
+        Some(Self {
+            burst: f64::from(config.burst.max(1)),
+            rate: f64::from(config.requests_per_minute) / 60.0,
+            trust_forwarded_for: config.trust_forwarded_for,
+            buckets: Mutex::default(),
+        })
+    }
+
+    /// Takes a token of the client. Returns the time until the next token if there are none.
+    fn acquire(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| self.tokens(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+        bucket.tokens = self.tokens(bucket, now);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Returns the tokens of the bucket refilled up to the moment.
+    fn tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+
+    /// Returns the address of the client. Behind a reverse proxy, it is the last address of
+    /// `X-Forwarded-For`, which the proxy appends to the addresses sent by the client. The
+    /// preceding addresses come from the client and can be forged.
+    fn client(&self, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| headers.get_all("x-forwarded-for").iter().next_back())
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next()?.trim().parse().ok());
+
+        forwarded.unwrap_or(peer.ip())
+    }
+}
+
+/// Middleware rejecting the requests of the clients that exceeded the limit with
+/// `429 Too Many Requests`.
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = limiter.client(request.headers(), peer);
+
+    if let Err(retry_after) = limiter.acquire(client, Instant::now()) {
+        let lang = Language::from_headers(request.headers());
+        let error = ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            lang.text("error.rate_limited"),
+        );
+
+        let seconds = retry_after_secs(retry_after);
+
+        return ([(header::RETRY_AFTER, seconds.to_string())], error).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Retry-After is in whole seconds, so the wait is rounded up.
+fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    fn limiter(requests_per_minute: u32, burst: u32, trust_forwarded_for: bool) -> RateLimiter {
+        RateLimiter::from_config(&RateLimitConfig {
+            requests_per_minute,
+            burst,
+            trust_forwarded_for,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn allows_burst_then_limits() {
+        let limiter = limiter(6, 3, false);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.acquire(CLIENT, now).is_ok());
+        }
+        assert_eq!(limiter.acquire(CLIENT, now), Err(Duration::from_secs(10)));
+
+        let other = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        assert!(limiter.acquire(other, now).is_ok());
+    }
+
+    #[test]
+    fn refills_tokens() {
+        let limiter = limiter(6, 2, false);
+        let now = Instant::now();
+
+        limiter.acquire(CLIENT, now).unwrap();
+        limiter.acquire(CLIENT, now).unwrap();
+
+        let later = now + Duration::from_secs(4);
+        assert_eq!(limiter.acquire(CLIENT, later), Err(Duration::from_secs(6)));
+
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.acquire(CLIENT, later).is_ok());
+        assert!(limiter.acquire(CLIENT, later).is_err());
+
+        // The bucket does not grow beyond the burst however long the client waits.
+        let later = later + Duration::from_secs(3600);
+        assert!(limiter.acquire(CLIENT, later).is_ok());
+        assert!(limiter.acquire(CLIENT, later).is_ok());
+        assert!(limiter.acquire(CLIENT, later).is_err());
+    }
+
+    #[test]
+    fn disables_limit_without_rate() {
+        assert!(
+            RateLimiter::from_config(&RateLimitConfig {
+                requests_per_minute: 0,
+                burst: 3,
+                trust_forwarded_for: false,
+            })
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn rounds_retry_after_up() {
+        assert_eq!(retry_after_secs(Duration::from_secs(10)), 10);
+        assert_eq!(retry_after_secs(Duration::from_millis(10_001)), 11);
+        assert_eq!(retry_after_secs(Duration::from_nanos(1)), 1);
+        assert_eq!(retry_after_secs(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn forgets_idle_clients_when_full() {
+        let limiter = limiter(60, 1, false);
+        let now = Instant::now();
+
+        for i in 0..MAX_TRACKED_CLIENTS as u32 {
+            limiter.acquire(IpAddr::from(i.to_be_bytes()), now).unwrap();
+        }
+
+        // Half a second later no bucket is full yet, so no client is forgotten.
+        let later = now + Duration::from_millis(500);
+        limiter.acquire(CLIENT, later).unwrap();
+        assert_eq!(
+            limiter.buckets.lock().unwrap().len(),
+            MAX_TRACKED_CLIENTS + 1
+        );
+
+        // A second later the buckets of the idle clients are full again and are dropped.
+        let later = now + Duration::from_secs(1);
+        limiter
+            .acquire(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)), later)
+            .unwrap();
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets.contains_key(&CLIENT));
+    }
+
+    #[test]
+    fn takes_client_address_added_by_proxy() {
+        let peer = SocketAddr::from(([10, 0, 0, 1], 443));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 192.0.2.1".parse().unwrap());
+
+        assert_eq!(limiter(6, 3, true).client(&headers, peer), CLIENT);
+        assert_eq!(limiter(6, 3, false).client(&headers, peer), peer.ip());
+
+        headers.append("x-forwarded-for", "198.51.100.4".parse().unwrap());
+        assert_eq!(
+            limiter(6, 3, true).client(&headers, peer),
+            IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4))
+        );
+
+        headers.insert("x-forwarded-for", "unknown".parse().unwrap());
+        assert_eq!(limiter(6, 3, true).client(&headers, peer), peer.ip());
+    }
+}
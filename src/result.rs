@@ -0,0 +1,130 @@
+use crate::i18n::Language;
+use crate::pgn::Game;
+
+/// Result of the game from the `Result` header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+
+    /// The game is still being played, `*` in PGN.
+    Ongoing,
+
+    /// The header is missing or has an unexpected value.
+    Unknown,
+}
+
+impl GameResult {
+    pub fn of(game: &Game) -> Self {
+        match game.header("Result").map(str::trim) {
+            Some("1-0") => Self::WhiteWins,
+            Some("0-1") => Self::BlackWins,
+            Some("1/2-1/2") | Some("½-½") => Self::Draw,
+            Some("*") => Self::Ongoing,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Returns points scored by White and Black, or `None` if the game has no result.
+    pub fn points(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::WhiteWins => Some(("1", "0")),
+            Self::BlackWins => Some(("0", "1")),
+            Self::Draw => Some(("½", "½")),
+            Self::Ongoing | Self::Unknown => None,
+        }
+    }
+
+    /// Describes how the game ended, using the `Termination` header and the last move. Lichess
+    /// reports checkmate, resignation and draws as `Normal` termination, so they are told apart
+    /// by the result and the last move.
+    pub fn termination(&self, game: &Game, lang: Language) -> &'static str {
+        let checkmate = game.moves.last().is_some_and(|m| m.san.ends_with('#'));
+
+        let termination = game
+            .header("Termination")
+            .and_then(termination_key)
+            .filter(|&key| key != "termination.normal");
+
+        let key = match (self, termination) {
+            (Self::Ongoing, _) => "result.ongoing",
+            (_, Some(key)) => key,
+            (Self::WhiteWins | Self::BlackWins, _) if checkmate => "result.checkmate",
+            (Self::WhiteWins | Self::BlackWins, _) => "result.resignation",
+            (Self::Draw, _) => "result.draw",
+            (Self::Unknown, _) => "result.unknown",
+        };
+
+        lang.text(key)
+    }
+}
+
+/// Returns the message key of the value of the `Termination` header used by Lichess.
+pub fn termination_key(termination: &str) -> Option<&'static str> {
+    match termination {
+        "Normal" => Some("termination.normal"),
+        "Time forfeit" => Some("termination.time_forfeit"),
+        "Abandoned" => Some("termination.abandoned"),
+        "Rules infraction" => Some("termination.rules_infraction"),
+        "Unterminated" => Some("termination.unterminated"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+
+    fn termination(headers: &str, moves: &str) -> &'static str {
+        let game = pgn::parse(&format!("{headers}\n\n{moves}"), Language::En).unwrap();
+        GameResult::of(&game).termination(&game, Language::En)
+    }
+
+    #[test]
+    fn describes_termination() {
+        let text = |key| Language::En.text(key);
+        let normal = "[Termination \"Normal\"]\n[Result \"1-0\"]";
+
+        assert_eq!(
+            termination(normal, "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0"),
+            text("result.checkmate")
+        );
+        assert_eq!(termination(normal, "1. e4 1-0"), text("result.resignation"));
+        assert_eq!(
+            termination(
+                "[Termination \"Normal\"]\n[Result \"1/2-1/2\"]",
+                "1. e4 1/2-1/2"
+            ),
+            text("result.draw")
+        );
+        assert_eq!(
+            termination(
+                "[Termination \"Time forfeit\"]\n[Result \"0-1\"]",
+                "1. e4 0-1"
+            ),
+            text("termination.time_forfeit")
+        );
+        assert_eq!(
+            termination("[Result \"*\"]", "1. e4 *"),
+            text("result.ongoing")
+        );
+        assert_eq!(termination("", "1. e4"), text("result.unknown"));
+    }
+
+    #[test]
+    fn maps_every_termination_to_message() {
+        for termination in [
+            "Normal",
+            "Time forfeit",
+            "Abandoned",
+            "Rules infraction",
+            "Unterminated",
+        ] {
+            let key = termination_key(termination).unwrap();
+            assert_ne!(Language::Ru.text(key), key);
+        }
+        assert_eq!(termination_key("Insufficient material"), None);
+    }
+}
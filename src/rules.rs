@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::i18n::Language;
+use crate::pgn::Game;
+
+/// Course rules the submitted games must comply with.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /// Allowed values of the `Variant` header. Empty list allows any variant.
+    pub variants: Vec<String>,
+
+    /// Whether the game must be rated (`true`) or casual (`false`). Any game is allowed if unset.
+    pub rated: Option<bool>,
+
+    /// Minimum estimated game duration in seconds: initial time + 40 × increment, the same
+    /// formula Lichess uses to tell bullet, blitz and rapid games apart.
+    pub min_time_control: Option<u64>,
+
+    /// Minimum number of full moves.
+    pub min_moves: Option<usize>,
+
+    /// Whether aborted games are allowed.
+    pub allow_aborted: bool,
+
+    /// First day of the semester. Games played before it are rejected.
+    #[serde(deserialize_with = "deserialize_date")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Last day of the semester. Games played after it are rejected.
+    #[serde(deserialize_with = "deserialize_date")]
+    pub end_date: Option<NaiveDate>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            variants: vec![String::from("Standard")],
+            rated: None,
+            min_time_control: None,
+            min_moves: None,
+            allow_aborted: false,
+            start_date: None,
+            end_date: None,
+        }
+    }
+}
+
+impl Rules {
+    /// Checks that the game complies with the rules.
+    pub fn check(&self, game: &Game, lang: Language) -> anyhow::Result<()> {
+        let variant = game.header("Variant").unwrap_or("Standard");
+        if !self.variants.is_empty() && !self.variants.iter().any(|v| v == variant) {
+            bail!(lang.format(
+                "rules.variant",
+                &[
+                    ("variant", &variant),
+                    ("allowed", &self.variants.join(", "))
+                ]
+            ));
+        }
+
+        if let Some(rated) = self.rated {
+            match (rated, game.is_rated()) {
+                (true, false) => bail!(lang.text("rules.rated")),
+                (false, true) => bail!(lang.text("rules.casual")),
+                _ => {}
+            }
+        }
+
+        if let Some(min_time_control) = self.min_time_control {
+            let Some((initial, increment)) = game.time_control() else {
+                bail!(lang.text("rules.time_control_required"));
+            };
+
+            let estimated = initial.as_secs() + 40 * increment.as_secs();
+            if estimated < min_time_control {
+                bail!(lang.format(
+                    "rules.time_control_too_short",
+                    &[
+                        ("minutes", &(initial.as_secs() / 60)),
+                        ("increment", &increment.as_secs()),
+                        ("estimated", &estimated),
+                        ("required", &min_time_control),
+                    ]
+                ));
+            }
+        }
+
+        // Lichess aborts the game if a player leaves before making the first move.
+        if !self.allow_aborted
+            && game.header("Termination") == Some("Abandoned")
+            && game.moves.len() < 2
+        {
+            bail!(lang.text("rules.aborted"));
+        }
+
+        if let Some(min_moves) = self.min_moves {
+            let moves = game.moves.len().div_ceil(2);
+            if moves < min_moves {
+                bail!(lang.format(
+                    "rules.too_few_moves",
+                    &[("moves", &moves), ("required", &min_moves)]
+                ));
+            }
+        }
+
+        if self.start_date.is_some() || self.end_date.is_some() {
+            let date = game
+                .header("UTCDate")
+                .or(game.header("Date"))
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y.%m.%d").ok());
+
+            let Some(date) = date else {
+                bail!(lang.text("rules.unknown_date"));
+            };
+
+            if self.start_date.is_some_and(|start| date < start)
+                || self.end_date.is_some_and(|end| date > end)
+            {
+                bail!(lang.format(
+                    "rules.outside_semester",
+                    &[("date", &date.format("%d.%m.%Y"))]
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rules for all tournaments, with optional overrides for individual tournaments.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSets {
+    pub default: Rules,
+
+    /// Rules of the tournaments by their exact name. They replace the default rules completely.
+    pub tournaments: HashMap<String, Rules>,
+}
+
+impl RuleSets {
+    /// Loads rules from the TOML file. The default rules are used if the file is not configured.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("cannot read rules from {}", path.display()))?;
+
+        toml::from_str(&content).with_context(|| format!("invalid rules in {}", path.display()))
+    }
+
+    /// Returns the rules of the tournament.
+    pub fn for_tournament(&self, tournament: &str) -> &Rules {
+        self.tournaments
+            .get(tournament.trim())
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Date written either as a native TOML date (`2025-09-01`) or as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateValue {
+    Native(toml::value::Datetime),
+    String(String),
+}
+
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let date = match DateValue::deserialize(deserializer)? {
+        DateValue::Native(datetime) => datetime.to_string(),
+        DateValue::String(string) => string,
+    };
+
+    NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| de::Error::custom(format!("invalid date {date}, expected YYYY-MM-DD")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+
+    const MOVES: &str = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 *";
+
+    fn game(headers: &[(&str, &str)], moves: &str) -> Game {
+        let headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("[{name} \"{value}\"]\n"))
+            .collect();
+
+        pgn::parse(&format!("{headers}\n{moves}"), Language::En).unwrap()
+    }
+
+    fn check(rules: &Rules, headers: &[(&str, &str)]) -> Result<(), String> {
+        rules
+            .check(&game(headers, MOVES), Language::En)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn checks_variant() {
+        let rules = Rules::default();
+
+        assert!(check(&rules, &[]).is_ok());
+        assert!(check(&rules, &[("Variant", "Standard")]).is_ok());
+        assert!(check(&rules, &[("Variant", "Atomic")]).is_err());
+
+        let any = Rules {
+            variants: Vec::new(),
+            ..Rules::default()
+        };
+        assert!(check(&any, &[("Variant", "Atomic")]).is_ok());
+    }
+
+    #[test]
+    fn checks_rated_flag() {
+        let rated = [("Event", "Hourly Blitz Arena"), ("WhiteRatingDiff", "+6")];
+        let casual = [("Event", "Rated games are not always rated")];
+
+        let rules = Rules {
+            rated: Some(true),
+            ..Rules::default()
+        };
+        assert!(check(&rules, &rated).is_ok());
+        assert!(check(&rules, &casual).is_err());
+
+        let rules = Rules {
+            rated: Some(false),
+            ..Rules::default()
+        };
+        assert!(check(&rules, &rated).is_err());
+        assert!(check(&rules, &casual).is_ok());
+    }
+
+    #[test]
+    fn checks_time_control() {
+        let rules = Rules {
+            min_time_control: Some(600),
+            ..Rules::default()
+        };
+
+        assert!(check(&rules, &[("TimeControl", "600+0")]).is_ok());
+        assert!(check(&rules, &[("TimeControl", "180+11")]).is_ok());
+        assert!(check(&rules, &[("TimeControl", "-")]).is_err());
+
+        let error = check(&rules, &[("TimeControl", "300+5")]).unwrap_err();
+        assert!(error.contains("5 min + 5 s"), "{error}");
+        assert!(error.contains("500 s"), "{error}");
+        assert!(error.contains("600 s"), "{error}");
+    }
+
+    #[test]
+    fn checks_aborted_games() {
+        let abandoned = [("Termination", "Abandoned")];
+        let rules = Rules::default();
+
+        assert!(
+            rules
+                .check(&game(&abandoned, "1. e4 *"), Language::En)
+                .is_err()
+        );
+        assert!(check(&rules, &abandoned).is_ok());
+
+        let rules = Rules {
+            allow_aborted: true,
+            ..Rules::default()
+        };
+        assert!(
+            rules
+                .check(&game(&abandoned, "1. e4 *"), Language::En)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn checks_number_of_moves() {
+        let rules = Rules {
+            min_moves: Some(5),
+            ..Rules::default()
+        };
+
+        assert!(check(&rules, &[]).is_ok());
+        assert!(
+            rules
+                .check(
+                    &game(&[], "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 *"),
+                    Language::En
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn checks_date() {
+        let rules = Rules {
+            start_date: NaiveDate::from_ymd_opt(2025, 9, 1),
+            end_date: NaiveDate::from_ymd_opt(2025, 12, 31),
+            ..Rules::default()
+        };
+
+        assert!(check(&rules, &[("UTCDate", "2025.09.01")]).is_ok());
+        assert!(check(&rules, &[("Date", "2025.12.31")]).is_ok());
+        assert!(check(&rules, &[("UTCDate", "2025.08.31")]).is_err());
+        assert!(check(&rules, &[("UTCDate", "2026.01.01")]).is_err());
+        assert!(check(&rules, &[("Date", "????.??.??")]).is_err());
+    }
+
+    #[test]
+    fn picks_tournament_rules() {
+        let rules: RuleSets = toml::from_str(
+            r#"
+            [default]
+            min_moves = 10
+
+            [tournaments."Блиц"]
+            start_date = 2025-09-01
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.for_tournament("Рапид").min_moves, Some(10));
+        assert_eq!(rules.for_tournament(" Блиц ").min_moves, None);
+        assert!(rules.for_tournament("Блиц").start_date.is_some());
+        assert!(toml::from_str::<RuleSets>("[default]\nmin_move = 1").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, bail};
+use shakmaty::Color;
+
+use crate::i18n::Language;
+use crate::pgn::Game;
+
+/// Games shorter than this number of half-moves are not compared by moves, as different games can
+/// easily share the same short sequence of moves.
+const MIN_COMPARED_PLIES: usize = 20;
+
+/// Index of the games submitted by the students, used to detect submitting the same game twice
+/// or a game of another student. The game played by two students against each other is
+/// submitted by both of them, once as White and once as Black, so only the claims on the same
+/// colour conflict.
+pub struct SubmissionIndex {
+    /// Tab-separated file the index is persisted to. The index is kept in memory only if unset.
+    path: Option<PathBuf>,
+    submissions: Mutex<Vec<Submission>>,
+}
+
+/// Submitted game, stored as a line of the index file.
+struct Submission {
+    game_id: String,
+    student_id: String,
+
+    /// Colour the student played the game with.
+    color: Color,
+
+    /// Hash of the moves, or `None` if the game is too short to be compared by moves.
+    moves_hash: Option<u64>,
+}
+
+impl SubmissionIndex {
+    /// Loads the index from the file. The file is created on the first submission. Malformed
+    /// lines, e.g. left by an interrupted write, are skipped with a warning.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self {
+                path: None,
+                submissions: Mutex::default(),
+            });
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("cannot read submissions from {}", path.display()));
+            }
+        };
+
+        let submissions = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .filter_map(|(i, line)| {
+                let submission = Submission::parse(line);
+                if submission.is_none() {
+                    tracing::warn!(path = %path.display(), line = i + 1, "skipping invalid submission");
+                }
+                submission
+            })
+            .collect();
+
+        Ok(Self {
+            path: Some(PathBuf::from(path)),
+            submissions: Mutex::new(submissions),
+        })
+    }
+
+    /// Checks that the student submits two different games that were not submitted by other
+    /// students, and adds the games to the index. The games are given as pairs of Lichess game
+    /// ID and the game itself.
+    pub fn register(
+        &self,
+        student_id: &str,
+        white: (&str, &Game),
+        black: (&str, &Game),
+        lang: Language,
+    ) -> anyhow::Result<()> {
+        let student_id = student_id.trim();
+        if student_id.is_empty() || student_id.chars().any(char::is_control) {
+            bail!(lang.text("submissions.invalid_student_id"));
+        }
+
+        let white = Submission::new(white.0, student_id, Color::White, white.1);
+        let black = Submission::new(black.0, student_id, Color::Black, black.1);
+
+        if white.game_id == black.game_id {
+            bail!(lang.text("submissions.same_game"));
+        }
+
+        if white.same_moves(&black) {
+            bail!(lang.text("submissions.same_moves"));
+        }
+
+        let mut submissions = self.submissions.lock().unwrap();
+
+        for (submission, color) in [(&white, "color.white"), (&black, "color.black")] {
+            let color = lang.text(color);
+
+            let others = submissions.iter().filter(|existing| {
+                existing.student_id != student_id && existing.color == submission.color
+            });
+
+            for existing in others {
+                if existing.game_id == submission.game_id {
+                    bail!(lang.format("submissions.already_submitted", &[("color", &color)]));
+                }
+
+                if existing.same_moves(submission) {
+                    bail!(lang.format("submissions.copied_moves", &[("color", &color)]));
+                }
+            }
+        }
+
+        // Resubmitting the same games does not add duplicate entries.
+        let new: Vec<Submission> = [white, black]
+            .into_iter()
+            .filter(|submission| {
+                !submissions.iter().any(|existing| {
+                    existing.game_id == submission.game_id
+                        && existing.student_id == submission.student_id
+                        && existing.color == submission.color
+                })
+            })
+            .collect();
+
+        if let Some(path) = &self.path
+            && !new.is_empty()
+        {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+            for submission in &new {
+                writeln!(file, "{}", submission.to_line())?;
+            }
+        }
+
+        submissions.extend(new);
+
+        Ok(())
+    }
+}
+
+impl Submission {
+    fn new(game_id: &str, student_id: &str, color: Color, game: &Game) -> Self {
+        let moves_hash = (game.moves.len() >= MIN_COMPARED_PLIES).then(|| {
+            let moves: Vec<&str> = game.moves.iter().map(|m| m.uci.as_str()).collect();
+            fnv1a(moves.join(" ").as_bytes())
+        });
+
+        Self {
+            game_id: String::from(game_id),
+            student_id: String::from(student_id),
+            color,
+            moves_hash,
+        }
+    }
+
+    fn same_moves(&self, other: &Self) -> bool {
+        self.moves_hash.is_some() && self.moves_hash == other.moves_hash
+    }
+
+    /// Parses `<game ID>\t<student ID>\t<moves hash or ->\t<w or b>` line.
+    fn parse(line: &str) -> Option<Self> {
+        let mut columns = line.split('\t');
+        let game_id = columns.next().filter(|id| !id.is_empty())?;
+        let student_id = columns.next().filter(|id| !id.is_empty())?;
+        let moves_hash = match columns.next()? {
+            "-" => None,
+            hash => Some(u64::from_str_radix(hash, 16).ok()?),
+        };
+        let color = Color::from_char(columns.next()?.parse().ok()?)?;
+        if columns.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            game_id: String::from(game_id),
+            student_id: String::from(student_id),
+            color,
+            moves_hash,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let moves_hash = match self.moves_hash {
+            Some(hash) => format!("{hash:016x}"),
+            None => String::from("-"),
+        };
+
+        format!(
+            "{}\t{}\t{moves_hash}\t{}",
+            self.game_id,
+            self.student_id,
+            self.color.char()
+        )
+    }
+}
+
+/// FNV-1a hash. Unlike the standard library hasher, it is stable between program versions, so
+/// the hashes can be persisted.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+    use crate::pgn;
+
+    fn game(moves: &str) -> Game {
+        pgn::parse(moves, Language::En).unwrap()
+    }
+
+    #[test]
+    fn stores_trimmed_student_id() {
+        let index = SubmissionIndex::load(None).unwrap();
+        let (white, black) = (game("1. e4 *"), game("1. d4 *"));
+
+        index
+            .register(
+                " 123456 ",
+                ("abcdefgh", &white),
+                ("ijklmnop", &black),
+                Language::En,
+            )
+            .unwrap();
+
+        let submissions = index.submissions.lock().unwrap();
+        assert_eq!(submissions[0].to_line(), "abcdefgh\t123456\t-\tw");
+    }
+
+    #[test]
+    fn rejects_control_characters_in_student_id() {
+        let index = SubmissionIndex::load(None).unwrap();
+        let (white, black) = (game("1. e4 *"), game("1. d4 *"));
+
+        for student_id in ["123\t456", "123\n456", "  "] {
+            let result = index.register(
+                student_id,
+                ("abcdefgh", &white),
+                ("ijklmnop", &black),
+                Language::En,
+            );
+            assert!(result.is_err(), "{student_id:?} is accepted");
+        }
+        assert!(index.submissions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn skips_invalid_lines() {
+        let path = env::temp_dir().join(format!("submissions-{}.tsv", process::id()));
+        fs::write(
+            &path,
+            "abcdefgh\t123456\t-\tw\nbroken\nzyxwvuts\t123456\t-\n\t123456\t-\nijklmnop\t123456\tnot-hex\n\
+             qrstuvwx\t654321\t00000000000000ff\tb\nyzabcdef\t654321\t-\tx\n",
+        )
+        .unwrap();
+
+        let index = SubmissionIndex::load(Some(&path));
+        fs::remove_file(&path).unwrap();
+
+        let submissions = index.unwrap().submissions.into_inner().unwrap();
+        let lines: Vec<_> = submissions.iter().map(Submission::to_line).collect();
+        assert_eq!(
+            lines,
+            [
+                "abcdefgh\t123456\t-\tw",
+                "qrstuvwx\t654321\t00000000000000ff\tb"
+            ]
+        );
+    }
+
+    #[test]
+    fn allows_opponents_to_submit_the_same_game() {
+        let index = SubmissionIndex::load(None).unwrap();
+        let moves = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 \
+                     8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 *";
+        let (shared, other, third) = (game(moves), game("1. d4 *"), game("1. c4 *"));
+
+        index
+            .register(
+                "111111",
+                ("abcdefgh", &shared),
+                ("ijklmnop", &other),
+                Language::En,
+            )
+            .unwrap();
+
+        // The opponent submits the game as Black.
+        index
+            .register(
+                "222222",
+                ("qrstuvwx", &third),
+                ("abcdefgh", &shared),
+                Language::En,
+            )
+            .unwrap();
+
+        // Another student claims the same colour in the same game, or copies its moves.
+        assert!(
+            index
+                .register(
+                    "333333",
+                    ("abcdefgh", &shared),
+                    ("yzabcdef", &third),
+                    Language::En
+                )
+                .is_err()
+        );
+        assert!(
+            index
+                .register(
+                    "333333",
+                    ("zzzzzzzz", &shared),
+                    ("yzabcdef", &third),
+                    Language::En
+                )
+                .is_err()
+        );
+
+        // Resubmission by the same student is allowed.
+        index
+            .register(
+                "111111",
+                ("abcdefgh", &shared),
+                ("ijklmnop", &other),
+                Language::En,
+            )
+            .unwrap();
+        assert_eq!(index.submissions.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::error::FieldError;
+use crate::i18n::Language;
+
+/// Formats of the text fields of the form. The defaults can be overridden for a particular
+/// university.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Formats {
+    pub student_name: FieldFormat,
+    pub student_group: FieldFormat,
+    pub student_id: FieldFormat,
+    pub subject_teacher: FieldFormat,
+    pub subject_tournament: FieldFormat,
+    pub game_opponent: FieldFormat,
+}
+
+/// Pattern the whole field value must match, and the error message if it does not. Without a
+/// custom message, the message for the field is taken from the message catalog.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldFormat {
+    #[serde(deserialize_with = "deserialize_pattern")]
+    pattern: Regex,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        // Surname and name, optionally followed by patronymic, e.g. `Иванов Иван Иванович`.
+        let full_name = r"\p{L}[\p{L}'-]*(\s+\p{L}[\p{L}'-]*){1,3}";
+
+        Self {
+            student_name: FieldFormat::new(full_name),
+            student_group: FieldFormat::new(r"\d{4}"),
+            student_id: FieldFormat::new(r"\d{4,10}"),
+            subject_teacher: FieldFormat::new(r".*\p{L}.*"),
+            subject_tournament: FieldFormat::new(r".*\S.*"),
+            game_opponent: FieldFormat::new(full_name),
+        }
+    }
+}
+
+impl Formats {
+    /// Loads formats from the TOML file. The fields missing in the file keep the default formats.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("cannot read formats from {}", path.display()))?;
+
+        toml::from_str(&content).with_context(|| format!("invalid formats in {}", path.display()))
+    }
+}
+
+impl FieldFormat {
+    fn new(pattern: &str) -> Self {
+        Self {
+            pattern: anchored(pattern).unwrap(),
+            message: None,
+        }
+    }
+
+    /// Checks the value and adds an error for the field if it is empty or does not match the
+    /// pattern. The value is expected to be trimmed.
+    pub fn validate(
+        &self,
+        value: &str,
+        field: &'static str,
+        lang: Language,
+        errors: &mut Vec<FieldError>,
+    ) {
+        if value.is_empty() {
+            errors.push(FieldError::new(
+                field,
+                "required",
+                lang.text("validation.required"),
+            ));
+        } else if !self.pattern.is_match(value) {
+            let key = format!("validation.{field}");
+            let message = match &self.message {
+                Some(message) => message.as_str(),
+                None => lang.text(&key),
+            };
+
+            errors.push(FieldError::new(field, "invalid_format", message));
+        }
+    }
+}
+
+/// Makes the pattern match the whole value rather than any part of it.
+fn anchored(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+fn deserialize_pattern<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    anchored(&pattern).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Data;
+
+    fn errors(format: &FieldFormat, value: &str) -> Vec<&'static str> {
+        let mut errors = Vec::new();
+        format.validate(value, "student.name", Language::En, &mut errors);
+        errors.into_iter().map(|error| error.code).collect()
+    }
+
+    fn is_valid(format: &FieldFormat, value: &str) -> bool {
+        errors(format, value).is_empty()
+    }
+
+    #[test]
+    fn validates_names() {
+        let formats = Formats::default();
+
+        for name in [
+            "Иванов Иван",
+            "Иванов Иван Иванович",
+            "O'Neil Mary-Jane",
+            "Ли  Мин",
+        ] {
+            assert!(is_valid(&formats.student_name, name), "{name} is rejected");
+            assert!(is_valid(&formats.game_opponent, name), "{name} is rejected");
+        }
+        for name in ["Иванов", "Иванов 1", "-Иван Иванов", "a b c d e"] {
+            assert!(!is_valid(&formats.student_name, name), "{name} is accepted");
+        }
+        assert_eq!(errors(&formats.student_name, ""), ["required"]);
+        assert_eq!(errors(&formats.student_name, "Иванов"), ["invalid_format"]);
+    }
+
+    #[test]
+    fn validates_group_and_student_id() {
+        let formats = Formats::default();
+
+        assert!(is_valid(&formats.student_group, "1234"));
+        assert!(!is_valid(&formats.student_group, "123"));
+        assert!(!is_valid(&formats.student_group, "12345"));
+
+        assert!(is_valid(&formats.student_id, "1234"));
+        assert!(is_valid(&formats.student_id, "1234567890"));
+        assert!(!is_valid(&formats.student_id, "12345678901"));
+        assert!(!is_valid(&formats.student_id, "12a4"));
+    }
+
+    #[test]
+    fn validates_subject() {
+        let formats = Formats::default();
+
+        assert!(is_valid(&formats.subject_teacher, "Петров П.П."));
+        assert!(!is_valid(&formats.subject_teacher, "123"));
+        assert!(is_valid(&formats.subject_tournament, "Турнир 1"));
+        assert!(!is_valid(&formats.subject_tournament, ""));
+    }
+
+    #[test]
+    fn trims_request_fields() {
+        let mut data = Data::default();
+        data.student.name = String::from(" Иванов Иван\t");
+        data.student.group = String::from("1234 ");
+        data.student.id = String::from(" 123456\n");
+        data.student.surname = Some(String::from(" "));
+        data.subject.teacher = String::from(" Петров П.П.");
+        data.subject.tournament = String::from("Турнир ");
+        data.game.opponent = String::from(" Сидоров Сидор ");
+        data.game.white_url = String::from(" https://lichess.org/abcdefgh");
+        data.game.black_url = String::from("https://lichess.org/ijklmnop ");
+
+        assert!(data.validate(&Formats::default()).is_ok());
+        assert_eq!(data.student.name, "Иванов Иван");
+        assert_eq!(data.student.id, "123456");
+        assert_eq!(data.student.surname, None);
+        assert_eq!(data.subject.tournament, "Турнир");
+        assert_eq!(data.game.white_url, "https://lichess.org/abcdefgh");
+    }
+
+    #[test]
+    fn rejects_links_to_other_hosts() {
+        let mut data = Data::default();
+        data.student.name = String::from("Иванов Иван");
+        data.student.group = String::from("1234");
+        data.student.id = String::from("123456");
+        data.subject.teacher = String::from("Петров П.П.");
+        data.subject.tournament = String::from("Турнир");
+        data.game.opponent = String::from("Сидоров Сидор");
+        data.game.white_url = String::from("https://example.com/abcdefgh");
+        data.game.black_url = String::from("http://lichess.org/ijklmnop");
+
+        let error = data.validate(&Formats::default()).unwrap_err();
+        let codes: Vec<_> = error
+            .errors
+            .iter()
+            .map(|error| (error.field, error.code))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ("game.white_url", "not_lichess_url"),
+                ("game.black_url", "not_lichess_url")
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_custom_formats() {
+        let formats: Formats = toml::from_str(
+            r#"
+            student_group = { pattern = "[А-Я]{2}-\\d{2}", message = "Группа вида ИС-21" }
+            "#,
+        )
+        .unwrap();
+
+        let mut errors = Vec::new();
+        formats
+            .student_group
+            .validate("1234", "student.group", Language::En, &mut errors);
+        assert_eq!(errors[0].message, "Группа вида ИС-21");
+        assert!(is_valid(&formats.student_group, "ИС-21"));
+        assert!(is_valid(&formats.student_id, "123456"));
+
+        assert!(toml::from_str::<Formats>(r#"student_id = { pattern = "(" }"#).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::i18n::Language;
+use crate::pgn::Game;
+
+/// Clock information written next to each move.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Clocks {
+    /// Clock column is not shown.
+    #[default]
+    Off,
+
+    /// Time left on the player's clock after the move.
+    Remaining,
+
+    /// Time the player spent thinking on the move.
+    Spent,
+}
+
+impl Clocks {
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::Off
+    }
+
+    /// Returns header of the clock column.
+    pub fn label(&self, lang: Language) -> Option<&'static str> {
+        match self {
+            Self::Off => None,
+            Self::Remaining => Some(lang.text("clocks.remaining")),
+            Self::Spent => Some(lang.text("clocks.spent")),
+        }
+    }
+
+    /// Formats clock of the half-move with the given index, counting from 0.
+    pub fn format(&self, game: &Game, ply: usize) -> Option<String> {
+        let time = match self {
+            Self::Off => return None,
+            Self::Remaining => game.moves.get(ply)?.clock?,
+            Self::Spent => game.time_spent(ply)?,
+        };
+
+        Some(format_duration(time))
+    }
+}
+
+/// Formats duration as `m:ss`, or `h:mm:ss` if it is longer than an hour.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
@@ -0,0 +1,269 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::data::Data;
+
+const DEFAULT_PATTERN: &str = "Otchet_TK_FViS_{student_id}_{date}";
+
+/// Placeholders that can be used in the pattern.
+const PLACEHOLDERS: [&str; 7] = [
+    "student_id",
+    "surname",
+    "surname_latin",
+    "group",
+    "tournament",
+    "tournament_latin",
+    "date",
+];
+
+/// Pattern of the report filename without the extension, e.g. `Otchet_{surname_latin}_{date}`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct FilenamePattern {
+    pattern: String,
+}
+
+impl Default for FilenamePattern {
+    fn default() -> Self {
+        Self {
+            pattern: String::from(DEFAULT_PATTERN),
+        }
+    }
+}
+
+impl FilenamePattern {
+    pub fn new(pattern: &str) -> anyhow::Result<Self> {
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                bail!("unclosed placeholder in filename pattern {pattern}");
+            };
+
+            let placeholder = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                bail!("unknown placeholder {{{placeholder}}} in filename pattern {pattern}");
+            }
+
+            rest = &rest[start + end + 1..];
+        }
+
+        Ok(Self {
+            pattern: String::from(pattern),
+        })
+    }
+
+    /// Returns the filename of the report with the `.xlsx` extension. The values are sanitized,
+    /// so that they cannot add directories or break the quoting of the header.
+    pub fn filename(&self, data: &Data, generation_time: DateTime<Local>) -> String {
+        let surname = data.student.person_name().surname;
+        let values = [
+            ("student_id", data.student.id.trim().to_string()),
+            ("surname_latin", transliterate(&surname)),
+            ("surname", surname),
+            ("group", data.student.group.trim().to_string()),
+            ("tournament", data.subject.tournament.trim().to_string()),
+            (
+                "tournament_latin",
+                transliterate(data.subject.tournament.trim()),
+            ),
+            ("date", generation_time.format("%Y-%m-%d").to_string()),
+        ];
+
+        // The pattern is scanned once, so placeholders inside the values are not expanded.
+        let mut name = String::with_capacity(self.pattern.len());
+        let mut rest = self.pattern.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+
+            name.push_str(&rest[..start]);
+            let placeholder = &rest[start + 1..start + end];
+            match values.iter().find(|(key, _)| *key == placeholder) {
+                Some((_, value)) => name.push_str(value),
+                None => name.push_str(&rest[start..=start + end]),
+            }
+
+            rest = &rest[start + end + 1..];
+        }
+        name.push_str(rest);
+
+        let name = sanitize(&name);
+        let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+        match name {
+            "" => String::from("report.xlsx"),
+            name => format!("{name}.xlsx"),
+        }
+    }
+}
+
+impl FromStr for FilenamePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> anyhow::Result<Self> {
+        Self::new(pattern)
+    }
+}
+
+impl TryFrom<String> for FilenamePattern {
+    type Error = anyhow::Error;
+
+    fn try_from(pattern: String) -> anyhow::Result<Self> {
+        Self::new(&pattern)
+    }
+}
+
+impl From<FilenamePattern> for String {
+    fn from(pattern: FilenamePattern) -> Self {
+        pattern.pattern
+    }
+}
+
+/// Replaces characters not allowed in filenames on common systems, quotes and control characters.
+pub fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Transliterates Russian letters to Latin according to the rules used in Russian passports.
+pub fn transliterate(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let latin = match lower {
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'д' => "d",
+            'е' | 'ё' | 'э' => "e",
+            'ж' => "zh",
+            'з' => "z",
+            'и' | 'й' => "i",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "kh",
+            'ц' => "ts",
+            'ч' => "ch",
+            'ш' => "sh",
+            'щ' => "shch",
+            'ъ' => "ie",
+            'ы' => "y",
+            'ь' => "",
+            'ю' => "iu",
+            'я' => "ia",
+            _ => {
+                result.push(c);
+                continue;
+            }
+        };
+
+        if c.is_uppercase() {
+            let mut chars = latin.chars();
+            if let Some(first) = chars.next() {
+                result.extend(first.to_uppercase());
+                result.push_str(chars.as_str());
+            }
+        } else {
+            result.push_str(latin);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn data(surname: &str, tournament: &str) -> Data {
+        let mut data = Data::default();
+        data.student.name = format!("{surname} Иван Иванович");
+        data.student.id = String::from(" 123456 ");
+        data.student.group = String::from("ИВТ-21");
+        data.subject.tournament = String::from(tournament);
+        data
+    }
+
+    fn filename(pattern: &str, data: &Data) -> String {
+        let time = Local.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap();
+        FilenamePattern::new(pattern).unwrap().filename(data, time)
+    }
+
+    #[test]
+    fn validates_placeholders() {
+        assert!(FilenamePattern::new("Otchet_{surname_latin}_{date}").is_ok());
+        assert!(FilenamePattern::new("report").is_ok());
+        assert!(FilenamePattern::new("Otchet_{name}").is_err());
+        assert!(FilenamePattern::new("Otchet_{date").is_err());
+    }
+
+    #[test]
+    fn substitutes_placeholders() {
+        let data = data("Петров", "Весенний турнир");
+
+        assert_eq!(
+            filename(DEFAULT_PATTERN, &data),
+            "Otchet_TK_FViS_123456_2025-03-14.xlsx"
+        );
+        assert_eq!(
+            filename("{surname}_{surname_latin}_{group}", &data),
+            "Петров_Petrov_ИВТ-21.xlsx"
+        );
+        assert_eq!(
+            filename("{tournament_latin}", &data),
+            "Vesennii turnir.xlsx"
+        );
+    }
+
+    #[test]
+    fn does_not_expand_placeholders_in_values() {
+        let data = data("Петров", "{date} {student_id}");
+
+        assert_eq!(
+            filename("{tournament}_{date}", &data),
+            "{date} {student_id}_2025-03-14.xlsx"
+        );
+    }
+
+    #[test]
+    fn sanitizes_filename() {
+        let data = data("Петров", "../Турнир: \"финал\"");
+
+        assert_eq!(filename("{tournament}", &data), "_Турнир_ _финал_.xlsx");
+        assert_eq!(filename("...", &data), "report.xlsx");
+        assert_eq!(sanitize("a/b\\c\n"), "a_b_c_");
+    }
+
+    #[test]
+    fn transliterates_russian_letters() {
+        assert_eq!(transliterate("Щукин"), "Shchukin");
+        assert_eq!(transliterate("Юлия Ёлкина"), "Iuliia Elkina");
+        assert_eq!(transliterate("Подъячев"), "Podieiachev");
+        assert_eq!(transliterate("Гоголь"), "Gogol");
+        assert_eq!(transliterate("Smith-Цой"), "Smith-Tsoi");
+    }
+}
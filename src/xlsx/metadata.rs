@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::i18n::Language;
+use crate::pgn::Game;
+use crate::result::termination_key;
+
+/// PGN header written in the metadata block under the game.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Metadata {
+    TimeControl,
+    WhiteElo,
+    BlackElo,
+    Termination,
+    Variant,
+    Link,
+}
+
+impl Metadata {
+    pub fn label(&self, lang: Language) -> &'static str {
+        lang.text(match self {
+            Self::TimeControl => "metadata.time_control",
+            Self::WhiteElo => "metadata.white_elo",
+            Self::BlackElo => "metadata.black_elo",
+            Self::Termination => "metadata.termination",
+            Self::Variant => "metadata.variant",
+            Self::Link => "metadata.link",
+        })
+    }
+
+    /// Returns human-readable value of the header, if the game has it.
+    pub fn value(&self, game: &Game, lang: Language) -> Option<String> {
+        let header = match self {
+            Self::TimeControl => "TimeControl",
+            Self::WhiteElo => "WhiteElo",
+            Self::BlackElo => "BlackElo",
+            Self::Termination => "Termination",
+            Self::Variant => "Variant",
+            Self::Link => "Site",
+        };
+        let value = game.header(header).filter(|value| *value != "?")?;
+
+        Some(match self {
+            Self::TimeControl => format_time_control(game, value, lang),
+            Self::Termination => String::from(termination_name(value, lang)),
+            _ => String::from(value),
+        })
+    }
+}
+
+/// Formats time control as `3 мин + 2 с` in the language of the report. Correspondence games have `-` as the time control.
+fn format_time_control(game: &Game, value: &str, lang: Language) -> String {
+    match game.time_control() {
+        Some((initial, increment)) => {
+            let initial = initial.as_secs();
+            let initial = match initial % 60 {
+                0 => lang.format("metadata.minutes", &[("value", &(initial / 60))]),
+                _ => lang.format("metadata.seconds", &[("value", &initial)]),
+            };
+            let increment = lang.format("metadata.seconds", &[("value", &increment.as_secs())]);
+
+            format!("{initial} + {increment}")
+        }
+        None if value == "-" => String::from(lang.text("metadata.no_time_control")),
+        None => String::from(value),
+    }
+}
+
+/// Translates values of the `Termination` header used by Lichess.
+fn termination_name(termination: &str, lang: Language) -> &str {
+    termination_key(termination).map_or(termination, |key| lang.text(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Data;
+    use crate::pgn;
+    use crate::validation::Formats;
+
+    fn game(headers: &str) -> Game {
+        pgn::parse(&format!("{headers}\n\n1. e4 *"), Language::En).unwrap()
+    }
+
+    #[test]
+    fn formats_headers() {
+        let game = game(
+            "[TimeControl \"90+5\"]\n[Termination \"Time forfeit\"]\n[WhiteElo \"?\"]\n\
+             [Site \"https://lichess.org/abcdefgh\"]",
+        );
+
+        assert_eq!(
+            Metadata::TimeControl.value(&game, Language::En).unwrap(),
+            "90 s + 5 s"
+        );
+        assert_eq!(
+            Metadata::Termination.value(&game, Language::En).unwrap(),
+            Language::En.text("termination.time_forfeit")
+        );
+        assert_eq!(Metadata::WhiteElo.value(&game, Language::En), None);
+        assert_eq!(
+            Metadata::Link.value(&game, Language::En).unwrap(),
+            "https://lichess.org/abcdefgh"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_terminations() {
+        let game = game("[Termination \"Insufficient material\"]");
+
+        assert_eq!(
+            Metadata::Termination.value(&game, Language::En).unwrap(),
+            "Insufficient material"
+        );
+    }
+
+    #[test]
+    fn removes_repeated_headers() {
+        let mut data = Data::default();
+        data.options.metadata = vec![
+            Metadata::Link,
+            Metadata::TimeControl,
+            Metadata::Link,
+            Metadata::TimeControl,
+        ];
+
+        let _ = data.validate(&Formats::default());
+
+        assert_eq!(
+            data.options.metadata,
+            [Metadata::Link, Metadata::TimeControl]
+        );
+    }
+}
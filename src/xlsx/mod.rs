@@ -1,8 +1,17 @@
+pub mod clocks;
+pub mod filename;
+pub mod metadata;
+pub mod notation;
 pub mod report;
 pub mod response;
+pub mod styles;
 
-mod styles;
 mod utils;
 
+pub use clocks::*;
+pub use filename::*;
+pub use metadata::*;
+pub use notation::*;
 pub use report::*;
 pub use response::*;
+pub use styles::Styles;
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Set of piece symbols used to write moves in the report.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Notation {
+    /// International notation with Latin letters: K, Q, R, B, N.
+    #[default]
+    Latin,
+
+    /// Russian notation used on paper scoresheets: Кр, Ф, Л, С, К.
+    Cyrillic,
+
+    /// Figurine notation: ♔, ♕, ♖, ♗, ♘.
+    Figurine,
+}
+
+impl Notation {
+    /// Replaces Latin piece letters in the algebraic notation of a move.
+    pub fn localize(&self, algebraic: &str) -> String {
+        algebraic.chars().fold(String::new(), |mut s, c| {
+            match self.piece_symbol(c) {
+                Some(symbol) => s.push_str(symbol),
+                None => s.push(c),
+            }
+            s
+        })
+    }
+
+    fn piece_symbol(&self, letter: char) -> Option<&'static str> {
+        let symbols = match self {
+            Self::Latin => return None,
+            Self::Cyrillic => ["Кр", "Ф", "Л", "С", "К"],
+            Self::Figurine => ["♔", "♕", "♖", "♗", "♘"],
+        };
+
+        match letter {
+            'K' => Some(symbols[0]),
+            'Q' => Some(symbols[1]),
+            'R' => Some(symbols[2]),
+            'B' => Some(symbols[3]),
+            'N' => Some(symbols[4]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_latin_moves() {
+        assert_eq!(Notation::Latin.localize("Nxe5+"), "Nxe5+");
+        assert_eq!(Notation::Latin.localize("e8=Q#"), "e8=Q#");
+    }
+
+    #[test]
+    fn localizes_cyrillic_moves() {
+        let notation = Notation::Cyrillic;
+
+        assert_eq!(notation.localize("Kd7"), "Крd7");
+        assert_eq!(notation.localize("Nf3"), "Кf3");
+        assert_eq!(notation.localize("Qxd8+"), "Фxd8+");
+        assert_eq!(notation.localize("Rb1"), "Лb1");
+        assert_eq!(notation.localize("Bxb5"), "Сxb5");
+        assert_eq!(notation.localize("exd8=N"), "exd8=К");
+        assert_eq!(notation.localize("e8=Q#"), "e8=Ф#");
+        assert_eq!(notation.localize("O-O-O"), "O-O-O");
+    }
+
+    #[test]
+    fn localizes_long_algebraic_moves() {
+        assert_eq!(Notation::Cyrillic.localize("Ng1-f3"), "Кg1-f3");
+        assert_eq!(Notation::Cyrillic.localize("Ke1xd2"), "Крe1xd2");
+        assert_eq!(Notation::Cyrillic.localize("e7xd8=Q+"), "e7xd8=Ф+");
+        assert_eq!(Notation::Figurine.localize("Bf1-b5"), "♗f1-b5");
+        assert_eq!(Notation::Figurine.localize("b7-b8=R"), "b7-b8=♖");
+    }
+}
@@ -1,39 +1,88 @@
-use anyhow::{anyhow, bail};
+use anyhow::bail;
 use chrono::{DateTime, Local};
-use pgnparse::parser::PgnInfo;
+use shakmaty::Color;
+use tracing::instrument;
 use umya_spreadsheet::{Border, Spreadsheet, Worksheet};
 
-use crate::data::Data;
-use crate::xlsx::{styles::Styles, utils::calc_row_count};
+use crate::config::{Config, ReportConfig};
+use crate::data::{Data, OptionsData};
+use crate::eval::{Eval, Judgement, Summary, biggest_mistakes};
+use crate::i18n::Language;
+use crate::name::PersonName;
+use crate::opening::Opening;
+use crate::pgn::{Game, Move};
+use crate::result::GameResult;
+use crate::xlsx::{
+    Metadata,
+    styles::Styles,
+    utils::{calc_row_count, range},
+};
 
 const MOVE_PLACEHOLDER: &str = "/";
 
+/// Written instead of the points if the game is unfinished or its result is unknown.
+const RESULT_PLACEHOLDER: &str = "—";
+
+/// Number of the biggest mistakes listed under each game.
+const MISTAKES_COUNT: usize = 3;
+
+/// Optional column written next to each move.
+enum Annotation {
+    Clock,
+    Eval,
+}
+
+impl Annotation {
+    fn label(&self, options: &OptionsData, lang: Language) -> &'static str {
+        match self {
+            Self::Clock => options.clocks.label(lang).unwrap_or_default(),
+            Self::Eval => lang.text("report.eval"),
+        }
+    }
+}
+
 pub struct Report {
     data: Data,
+    game_white: Game,
+    game_black: Game,
     generation_time: DateTime<Local>,
+    lang: Language,
+    config: ReportConfig,
     style: Styles,
 }
 
 impl Report {
-    pub fn new(data: Data) -> Self {
+    pub fn new(data: Data, game_white: Game, game_black: Game, config: &Config) -> Self {
         Self {
+            lang: data.options.language(),
+            config: config.report.clone(),
             data,
+            game_white,
+            game_black,
             generation_time: Local::now(),
-            style: Styles::default(),
+            style: config.styles.clone(),
         }
     }
 
+    pub fn generation_time(&self) -> DateTime<Local> {
+        self.generation_time
+    }
+
     pub fn filename(&self) -> String {
-        format!(
-            "Otchet_TK_FViS_{}_{}.xlsx",
-            self.data.student.id,
-            self.generation_time.format("%Y-%m-%d"),
-        )
+        self.config
+            .filename_pattern
+            .filename(&self.data, self.generation_time)
+    }
+
+    /// Returns the form data and the games of White and Black the report was built from.
+    pub fn into_parts(self) -> (Data, Game, Game) {
+        (self.data, self.game_white, self.game_black)
     }
 }
 
 impl Report {
-    pub async fn generate_spreadsheet(&self) -> anyhow::Result<Spreadsheet> {
+    #[instrument(skip_all, err)]
+    pub fn generate_spreadsheet(&self) -> anyhow::Result<Spreadsheet> {
         let mut book = umya_spreadsheet::new_file();
         let sheet = match book.get_sheet_by_name_mut("Sheet1") {
             Some(s) => s,
@@ -43,86 +92,93 @@ impl Report {
         self.write_title(sheet);
         self.write_info(sheet);
         self.write_game_info(sheet);
-        self.write_games(sheet).await?;
+        self.write_games(sheet)?;
 
         Ok(book)
     }
 
     fn write_title(&self, sheet: &mut Worksheet) {
-        let title = r#"Отчет о результатах самостоятельной работы обучающегося по дисциплинам "Физическая культура" или "Элективные курсы по физической культуре и спорту""#;
+        let last_col = 1 + 2 * self.game_width();
 
-        sheet.add_merge_cells("B1:M1");
+        sheet.add_merge_cells(range((2, 1), (last_col, 1)));
         sheet
-            .get_cell_mut("B1")
-            .set_value(title)
+            .get_cell_mut((2, 1))
+            .set_value(
+                self.config
+                    .title
+                    .as_deref()
+                    .unwrap_or(self.lang.text("report.title")),
+            )
             .set_style(self.style.title());
     }
 
     fn write_info(&self, sheet: &mut Worksheet) {
-        sheet
-            .get_cell_mut("B3")
-            .set_value("Студ. билет")
-            .set_style(self.style.info_table());
-        sheet
-            .get_cell_mut("B4")
-            .set_value(&self.data.student.id)
-            .set_style(self.style.info_table());
-
-        sheet.add_merge_cells("C3:F3");
-        sheet.add_merge_cells("C4:F4");
-        sheet
-            .get_cell_mut("C3")
-            .set_value("ФИО")
-            .set_style(self.style.info_table());
-        sheet
-            .get_cell_mut("C4")
-            .set_value(&self.data.student.name)
-            .set_style(self.style.info_table());
-
-        sheet
-            .get_cell_mut("G3")
-            .set_value("Группа")
-            .set_style(self.style.info_table());
-        sheet
-            .get_cell_mut("G4")
-            .set_value(&self.data.student.group)
-            .set_style(self.style.info_table());
-
-        sheet.add_merge_cells("H3:J3");
-        sheet.add_merge_cells("H4:J4");
-        sheet
-            .get_cell_mut("H3")
-            .set_value("Спортивное отделение")
-            .set_style(self.style.info_table());
-        sheet
-            .get_cell_mut("H4")
-            .set_value("Шахматы")
-            .set_style(self.style.info_table());
+        let cells = [
+            (
+                self.lang.text("report.student_id"),
+                self.data.student.id.as_str(),
+            ),
+            (
+                self.lang.text("report.full_name"),
+                self.data.student.name.as_str(),
+            ),
+            (
+                self.lang.text("report.group"),
+                self.data.student.group.as_str(),
+            ),
+            (
+                self.lang.text("report.sports_section"),
+                self.lang.text("report.chess"),
+            ),
+            (
+                self.lang.text("report.teacher"),
+                self.data.subject.teacher.as_str(),
+            ),
+        ];
+
+        for ((label, value), (first_col, last_col)) in cells.into_iter().zip(self.info_columns()) {
+            for (row, text) in [(3, label), (4, value)] {
+                if last_col > first_col {
+                    sheet.add_merge_cells(range((first_col, row), (last_col, row)));
+                }
+                sheet
+                    .get_cell_mut((first_col, row))
+                    .set_value(text)
+                    .set_style(self.style.info_table());
+            }
+        }
 
-        sheet.add_merge_cells("K3:M3");
-        sheet.add_merge_cells("K4:M4");
-        sheet
-            .get_cell_mut("K3")
-            .set_value("Преподаватель")
-            .set_style(self.style.info_table());
-        sheet
-            .get_cell_mut("K4")
-            .set_value(&self.data.subject.teacher)
-            .set_style(self.style.info_table());
+        // Fix right borders of the info table due to the last cells being merged.
+        let last_col = 1 + 2 * self.game_width();
+        for row in [3, 4] {
+            sheet
+                .get_cell_mut((last_col, row))
+                .get_style_mut()
+                .get_borders_mut()
+                .get_right_mut()
+                .set_border_style(Border::BORDER_MEDIUM);
+        }
+    }
 
-        // Fix right borders of the subject info table due to K3:M3 and K4:M4 being merged.
-        sheet
-            .get_cell_mut("M3")
-            .get_style_mut()
-            .get_borders_mut()
-            .get_right_mut()
-            .set_border_style(Border::BORDER_MEDIUM);
-        sheet
-            .get_cell_mut("M4")
-            .get_style_mut()
-            .get_borders_mut()
-            .get_right_mut()
-            .set_border_style(Border::BORDER_MEDIUM);
+    /// Returns the first and the last columns of the student ID, name, group, section and
+    /// teacher cells of the info table. The table spans both games, and the columns added by
+    /// the annotations widen the name, section and teacher cells.
+    fn info_columns(&self) -> [(u32, u32); 5] {
+        // Widths of the cells with no annotations, when the games span 12 columns.
+        const BASE_WIDTHS: [u32; 5] = [1, 4, 1, 3, 3];
+
+        let extra = 2 * self.game_width() - BASE_WIDTHS.iter().sum::<u32>();
+        let mut widths = BASE_WIDTHS;
+        widths[3] += extra / 3;
+        widths[4] += extra / 3;
+        widths[1] += extra - 2 * (extra / 3);
+
+        let mut first_col = 2;
+        widths.map(|width| {
+            let columns = (first_col, first_col + width - 1);
+            first_col += width;
+            columns
+        })
     }
 
     fn write_game_info(&self, sheet: &mut Worksheet) {
@@ -131,94 +187,93 @@ impl Report {
             self.data.subject.tournament,
             self.generation_time.format("%d.%m.%Y"),
         );
+        let student = self
+            .data
+            .student
+            .person_name()
+            .format(self.config.name_format);
+        let opponent = PersonName::parse(&self.data.game.opponent).format(self.config.name_format);
+
+        self.write_game_header(sheet, &self.game_white, 0, &event_info, &student, &opponent);
+        self.write_game_header(sheet, &self.game_black, 1, &event_info, &opponent, &student);
+    }
 
-        sheet.add_merge_cells("B7:G7");
-        sheet.add_merge_cells("B8:G8");
-        sheet
-            .get_cell_mut("B7")
-            .set_value("Шахматная партия №1")
-            .set_style(self.style.header());
-        sheet
-            .get_cell_mut("B8")
-            .set_value(&event_info)
-            .set_style(self.style.header());
-
-        sheet
-            .get_cell_mut("B9")
-            .set_value("Белые")
-            .set_style(self.style.game_info_table());
-        sheet
-            .get_cell_mut("B10")
-            .set_value("Черные")
-            .set_style(self.style.game_info_table());
-
-        sheet.add_merge_cells("C9:G9");
-        sheet.add_merge_cells("C10:G10");
-        sheet
-            .get_cell_mut("C9")
-            .set_value(self.data.student.short_name())
-            .set_style(self.style.student_name());
-        sheet
-            .get_cell_mut("C10")
-            .set_value(&self.data.game.opponent)
-            .set_style(self.style.student_name());
-
-        sheet.add_merge_cells("H7:M7");
-        sheet.add_merge_cells("H8:M8");
-        sheet
-            .get_cell_mut("H7")
-            .set_value("Шахматная партия №2")
+    fn write_game_header(
+        &self,
+        sheet: &mut Worksheet,
+        game: &Game,
+        index: u32,
+        event_info: &str,
+        white: &str,
+        black: &str,
+    ) {
+        let first_col = index * self.game_width() + 2;
+        let last_col = first_col + self.game_width() - 1;
+
+        sheet.add_merge_cells(range((first_col, 7), (last_col, 7)));
+        sheet.add_merge_cells(range((first_col, 8), (last_col, 8)));
+        sheet
+            .get_cell_mut((first_col, 7))
+            .set_value(
+                self.lang
+                    .format("report.game_number", &[("number", &(index + 1))]),
+            )
             .set_style(self.style.header());
         sheet
-            .get_cell_mut("H8")
-            .set_value(&event_info)
+            .get_cell_mut((first_col, 8))
+            .set_value(event_info)
             .set_style(self.style.header());
 
         sheet
-            .get_cell_mut("H9")
-            .set_value("Белые")
+            .get_cell_mut((first_col, 9))
+            .set_value(self.lang.text("report.white"))
             .set_style(self.style.game_info_table());
         sheet
-            .get_cell_mut("H10")
-            .set_value("Черные")
+            .get_cell_mut((first_col, 10))
+            .set_value(self.lang.text("report.black"))
             .set_style(self.style.game_info_table());
 
-        sheet.add_merge_cells("I9:M9");
-        sheet.add_merge_cells("I10:M10");
+        sheet.add_merge_cells(range((first_col + 1, 9), (last_col, 9)));
+        sheet.add_merge_cells(range((first_col + 1, 10), (last_col, 10)));
         sheet
-            .get_cell_mut("I9")
-            .set_value(&self.data.game.opponent)
+            .get_cell_mut((first_col + 1, 9))
+            .set_value(white)
             .set_style(self.style.student_name());
         sheet
-            .get_cell_mut("I10")
-            .set_value(self.data.student.short_name())
+            .get_cell_mut((first_col + 1, 10))
+            .set_value(black)
             .set_style(self.style.student_name());
 
-        // Fix right borders of the game info table due to I9:M9 and I10:M10 being merged.
+        // Fix right borders of the game info table due to the player names being merged.
         sheet
-            .get_cell_mut("M9")
+            .get_cell_mut((last_col, 9))
             .get_style_mut()
             .get_borders_mut()
             .get_right_mut()
             .set_border_style(Border::BORDER_THIN);
         sheet
-            .get_cell_mut("M10")
+            .get_cell_mut((last_col, 10))
             .get_style_mut()
             .get_borders_mut()
             .get_right_mut()
             .set_border_style(Border::BORDER_THIN);
-    }
 
-    async fn write_games(&self, sheet: &mut Worksheet) -> anyhow::Result<()> {
-        let (mut game_white, mut game_black) = tokio::try_join!(
-            self.data.load_game_as_white(),
-            self.data.load_game_as_black()
-        )?;
+        if let Some(opening) = Opening::of(game) {
+            sheet.add_merge_cells(range((first_col, 11), (last_col, 11)));
+            sheet
+                .get_cell_mut((first_col, 11))
+                .set_value(format!("{} {}", opening.eco, opening.name))
+                .set_style(self.style.game_opening());
+        }
+    }
 
-        let moves = calc_row_count(game_white.moves.len(), game_black.moves.len());
+    fn write_games(&self, sheet: &mut Worksheet) -> anyhow::Result<()> {
+        // A game starting with Black's move leaves the first White cell empty.
+        let plies = |game: &Game| game.moves.len() + game.first_move().1;
+        let moves = calc_row_count(plies(&self.game_white), plies(&self.game_black));
 
-        self.write_game(sheet, &mut game_white, moves, 0)?;
-        self.write_game(sheet, &mut game_black, moves, 1)?;
+        self.write_game(sheet, &self.game_white, moves, 0)?;
+        self.write_game(sheet, &self.game_black, moves, 1)?;
 
         Ok(())
     }
@@ -226,119 +281,91 @@ impl Report {
     fn write_game(
         &self,
         sheet: &mut Worksheet,
-        pgn: &mut PgnInfo,
+        game: &Game,
         moves: u32,
         index: u32,
     ) -> anyhow::Result<()> {
-        let base_col = index * 6 + 2;
+        let base_col = index * self.game_width() + 2;
         let base_row = 14;
         let height = moves / 2 + 2;
 
-        sheet.get_cell_mut((base_col, 13)).set_value("№");
-        sheet.get_cell_mut((base_col + 1, 13)).set_value("Белые");
-        sheet.get_cell_mut((base_col + 2, 13)).set_value("Черные");
-        sheet.get_cell_mut((base_col + 3, 13)).set_value("№");
-        sheet.get_cell_mut((base_col + 4, 13)).set_value("Белые");
-        sheet.get_cell_mut((base_col + 5, 13)).set_value("Черные");
-
-        for i in base_row..base_row + height {
-            let move_index = i - base_row;
+        // The moves are written in two halves, each with its own move number column.
+        let second_col = base_col + self.half_width();
+        let white_col = 1;
+        let black_col = 1 + self.move_width();
 
+        for col in [base_col, second_col] {
             sheet
-                .get_cell_mut((base_col, i))
-                .set_value_number(move_index + 1)
-                .set_style(self.style.game_move_number());
-
-            let white_move_index = (2 * move_index) as usize;
-            let white_move = if white_move_index < pgn.moves.len() {
-                pgn.moves[white_move_index].san.as_str()
-            } else {
-                MOVE_PLACEHOLDER
-            };
-
+                .get_cell_mut((col, 13))
+                .set_value(self.lang.text("report.move_number"));
             sheet
-                .get_cell_mut((base_col + 1, i))
-                .set_value(white_move)
-                .set_style(self.style.game_move());
-
-            let black_move_index = (2 * move_index + 1) as usize;
-            let black_move = if black_move_index < pgn.moves.len() {
-                pgn.moves[black_move_index].san.as_str()
-            } else {
-                MOVE_PLACEHOLDER
-            };
-
+                .get_cell_mut((col + white_col, 13))
+                .set_value(self.lang.text("report.white"));
             sheet
-                .get_cell_mut((base_col + 2, i))
-                .set_value(black_move)
-                .set_style(self.style.game_move());
+                .get_cell_mut((col + black_col, 13))
+                .set_value(self.lang.text("report.black"));
+
+            for (i, annotation) in self.annotations().into_iter().enumerate() {
+                let label = annotation.label(&self.data.options, self.lang);
+                let offset = i as u32 + 1;
+
+                sheet
+                    .get_cell_mut((col + white_col + offset, 13))
+                    .set_value(label);
+                sheet
+                    .get_cell_mut((col + black_col + offset, 13))
+                    .set_value(label);
+            }
+        }
+
+        for i in base_row..base_row + height {
+            self.write_move_row(sheet, game, base_col, i, i - base_row);
         }
 
         let move_offset = height;
         let height = height - 4;
 
         for i in base_row..base_row + height {
-            let move_index = i - base_row + move_offset;
-
-            sheet
-                .get_cell_mut((base_col + 3, i))
-                .set_value_number(i - base_row + 1 + move_offset)
-                .set_style(self.style.game_move_number());
-
-            let white_move_index = (2 * move_index) as usize;
-            let white_move = if white_move_index < pgn.moves.len() {
-                pgn.moves[white_move_index].san.as_str()
-            } else {
-                MOVE_PLACEHOLDER
-            };
-
-            sheet
-                .get_cell_mut((base_col + 4, i))
-                .set_value(white_move)
-                .set_style(self.style.game_move());
-
-            let black_move_index = (2 * move_index + 1) as usize;
-            let black_move = if black_move_index < pgn.moves.len() {
-                pgn.moves[black_move_index].san.as_str()
-            } else {
-                MOVE_PLACEHOLDER
-            };
-
-            sheet
-                .get_cell_mut((base_col + 5, i))
-                .set_value(black_move)
-                .set_style(self.style.game_move());
+            self.write_move_row(sheet, game, second_col, i, i - base_row + move_offset);
         }
 
-        let result = pgn.get_header("Result");
-        let mut r = result.split('-');
-        let result_white = r.next().ok_or(anyhow!("failed to get result"))?;
-        let result_black = r.next().ok_or(anyhow!("failed to get result"))?;
-
+        let result = GameResult::of(game);
+        let (result_white, result_black) = result
+            .points()
+            .unwrap_or((RESULT_PLACEHOLDER, RESULT_PLACEHOLDER));
         sheet
-            .get_cell_mut((base_col + 3, base_row + height + 2))
-            .set_value("Итог:")
+            .get_cell_mut((second_col, base_row + height + 2))
+            .set_value(self.lang.text("report.result"))
             .set_style(self.style.game_result());
         sheet
-            .get_cell_mut((base_col + 4, base_row + height + 1))
-            .set_value("Белые")
+            .get_cell_mut((second_col + white_col, base_row + height + 1))
+            .set_value(self.lang.text("report.white"))
             .set_style(self.style.game_result());
         sheet
-            .get_cell_mut((base_col + 4, base_row + height + 2))
+            .get_cell_mut((second_col + white_col, base_row + height + 2))
             .set_value(result_white)
             .set_style(self.style.game_result());
         sheet
-            .get_cell_mut((base_col + 5, base_row + height + 1))
-            .set_value("Черные")
+            .get_cell_mut((second_col + black_col, base_row + height + 1))
+            .set_value(self.lang.text("report.black"))
             .set_style(self.style.game_result());
         sheet
-            .get_cell_mut((base_col + 5, base_row + height + 2))
+            .get_cell_mut((second_col + black_col, base_row + height + 2))
             .set_value(result_black)
             .set_style(self.style.game_result());
 
+        sheet
+            .get_cell_mut((second_col + white_col, base_row + height + 3))
+            .set_value(result.termination(game, self.lang))
+            .set_style(self.style.game_result());
+        sheet.add_merge_cells(range(
+            (second_col + white_col, base_row + height + 3),
+            (second_col + self.half_width() - 1, base_row + height + 3),
+        ));
+
         for i in 0..4 {
             sheet
-                .get_cell_mut((base_col + 3, base_row + height + i))
+                .get_cell_mut((second_col, base_row + height + i))
                 .set_style(self.style.game_move_number_filler());
         }
 
@@ -346,7 +373,7 @@ impl Report {
         // inherited from the move number style.
 
         // Top and bottom border of game moves area.
-        for i in 0..=5 {
+        for i in 0..self.game_width() {
             sheet
                 .get_cell_mut((base_col + i, base_row))
                 .get_style_mut()
@@ -365,13 +392,352 @@ impl Report {
         // Right border of game moves area.
         for row in 0..height + 4 {
             sheet
-                .get_cell_mut((base_col + 5, base_row + row))
+                .get_cell_mut((base_col + self.game_width() - 1, base_row + row))
                 .get_style_mut()
                 .get_borders_mut()
                 .get_right_mut()
                 .set_border_style(Border::BORDER_MEDIUM);
         }
 
+        let mut row = base_row + height + 5;
+
+        if self.data.options.show_evals() {
+            row = self.write_summary(sheet, game, base_col, row);
+        }
+
+        self.write_metadata(sheet, game, base_col, row);
+
         Ok(())
     }
+
+    /// Writes the move number and both half-moves of a single full move starting at `col`. The
+    /// rows start from the first move of the game, which may be Black's move.
+    fn write_move_row(
+        &self,
+        sheet: &mut Worksheet,
+        game: &Game,
+        col: u32,
+        row: u32,
+        move_index: u32,
+    ) {
+        let (first_number, first_ply) = game.first_move();
+
+        sheet
+            .get_cell_mut((col, row))
+            .set_value_number(first_number + move_index)
+            .set_style(self.style.game_move_number());
+
+        for (offset, cell) in [(0, 2 * move_index), (1, 2 * move_index + 1)] {
+            let move_col = col + 1 + offset * self.move_width();
+            let ply = (cell as usize).checked_sub(first_ply);
+            let m = ply.and_then(|ply| game.moves.get(ply));
+
+            let value = match m {
+                Some(m) => self.format_move(m),
+                None => String::from(MOVE_PLACEHOLDER),
+            };
+
+            let (value, style) = match ply.and_then(|ply| Judgement::of(game, ply)) {
+                Some(judgement) if self.data.options.show_evals() => (
+                    value + judgement.symbol(),
+                    self.style.game_move_judgement(judgement),
+                ),
+                _ => (value, self.style.game_move()),
+            };
+
+            sheet
+                .get_cell_mut((move_col, row))
+                .set_value(value)
+                .set_style(style);
+
+            for (i, annotation) in self.annotations().into_iter().enumerate() {
+                let value = match annotation {
+                    Annotation::Clock => {
+                        ply.and_then(|ply| self.data.options.clocks.format(game, ply))
+                    }
+                    Annotation::Eval => m.and_then(|m| m.eval).map(|eval| eval.to_string()),
+                };
+
+                sheet
+                    .get_cell_mut((move_col + 1 + i as u32, row))
+                    .set_value_string(value.unwrap_or_default())
+                    .set_style(self.style.game_annotation());
+            }
+        }
+    }
+
+    /// Writes accuracy and the number of inaccuracies, mistakes and blunders of both players
+    /// under the moves table. Returns the first row after the summary.
+    fn write_summary(&self, sheet: &mut Worksheet, game: &Game, col: u32, row: u32) -> u32 {
+        let white = Summary::of(game, Color::White);
+        let black = Summary::of(game, Color::Black);
+
+        let white_col = col + self.half_width() + 1;
+        let black_col = white_col + self.move_width();
+
+        let format_accuracy = |summary: &Summary| match summary.accuracy {
+            Some(accuracy) => format!("{accuracy:.0}%"),
+            None => String::from("—"),
+        };
+
+        let lines = [
+            (
+                String::from(self.lang.text("report.accuracy")),
+                format_accuracy(&white),
+                format_accuracy(&black),
+            ),
+            (
+                self.lang.format(
+                    "report.inaccuracies",
+                    &[("symbol", &Judgement::Inaccuracy.symbol())],
+                ),
+                white.inaccuracies.to_string(),
+                black.inaccuracies.to_string(),
+            ),
+            (
+                self.lang.format(
+                    "report.mistakes",
+                    &[("symbol", &Judgement::Mistake.symbol())],
+                ),
+                white.mistakes.to_string(),
+                black.mistakes.to_string(),
+            ),
+            (
+                self.lang.format(
+                    "report.blunders",
+                    &[("symbol", &Judgement::Blunder.symbol())],
+                ),
+                white.blunders.to_string(),
+                black.blunders.to_string(),
+            ),
+        ];
+
+        sheet
+            .get_cell_mut((white_col, row))
+            .set_value(self.lang.text("report.white"))
+            .set_style(self.style.game_result());
+        sheet
+            .get_cell_mut((black_col, row))
+            .set_value(self.lang.text("report.black"))
+            .set_style(self.style.game_result());
+
+        let summary_height = lines.len() as u32;
+
+        for (i, (label, white, black)) in lines.into_iter().enumerate() {
+            let row = row + 1 + i as u32;
+
+            sheet.add_merge_cells(range((col, row), (white_col - 1, row)));
+            sheet
+                .get_cell_mut((col, row))
+                .set_value(label)
+                .set_style(self.style.game_summary_label());
+            sheet
+                .get_cell_mut((white_col, row))
+                .set_value(white)
+                .set_style(self.style.game_result());
+            sheet
+                .get_cell_mut((black_col, row))
+                .set_value(black)
+                .set_style(self.style.game_result());
+        }
+
+        let row = row + summary_height + 2;
+        let last_col = col + self.game_width() - 1;
+
+        // The space for the mistakes is always reserved, so that the blocks below are aligned
+        // between the games.
+        let next_row = row + MISTAKES_COUNT as u32 + 2;
+
+        let mistakes = biggest_mistakes(game, MISTAKES_COUNT);
+        if mistakes.is_empty() {
+            return next_row;
+        }
+
+        sheet.add_merge_cells(range((col, row), (last_col, row)));
+        sheet
+            .get_cell_mut((col, row))
+            .set_value(self.lang.text("report.biggest_mistakes"))
+            .set_style(self.style.header());
+
+        for (i, ply) in mistakes.into_iter().enumerate() {
+            let row = row + 1 + i as u32;
+            let m = &game.moves[ply];
+
+            let number = match m.color {
+                Color::White => format!("{}.", m.number),
+                Color::Black => format!("{}...", m.number),
+            };
+            let before = match ply.checked_sub(1) {
+                Some(previous) => game.moves[previous].eval,
+                None => Some(Eval::Centipawns(0)),
+            };
+            let symbol = Judgement::of(game, ply).map_or("", |j| j.symbol());
+
+            let mut text = format!("{number} {}{symbol}", self.format_move(m));
+            if let (Some(before), Some(after)) = (before, m.eval) {
+                text.push_str(&format!(" ({before} → {after})"));
+            }
+
+            sheet.add_merge_cells(range((col, row), (last_col, row)));
+            sheet
+                .get_cell_mut((col, row))
+                .set_value(text)
+                .set_style(self.style.game_summary_label());
+        }
+
+        next_row
+    }
+
+    /// Writes the selected PGN headers under the moves table. Returns the first row after them.
+    fn write_metadata(&self, sheet: &mut Worksheet, game: &Game, col: u32, row: u32) -> u32 {
+        let value_col = col + self.half_width();
+        let last_col = col + self.game_width() - 1;
+
+        for (i, metadata) in self.data.options.metadata.iter().enumerate() {
+            let row = row + i as u32;
+            let value = metadata.value(game, self.lang);
+
+            sheet.add_merge_cells(range((col, row), (value_col - 1, row)));
+            sheet.add_merge_cells(range((value_col, row), (last_col, row)));
+            sheet
+                .get_cell_mut((col, row))
+                .set_value(metadata.label(self.lang))
+                .set_style(self.style.game_summary_label());
+
+            let cell = sheet.get_cell_mut((value_col, row));
+            cell.set_value_string(value.as_deref().unwrap_or("—"))
+                .set_style(self.style.game_summary_label());
+
+            if let (Metadata::Link, Some(url)) = (metadata, value) {
+                cell.get_hyperlink_mut().set_url(url);
+                cell.set_style(self.style.game_link());
+            }
+        }
+
+        row + self.data.options.metadata.len() as u32 + 1
+    }
+
+    /// Returns the enabled columns written next to each move.
+    fn annotations(&self) -> Vec<Annotation> {
+        let mut annotations = Vec::new();
+
+        if self.data.options.clocks.is_enabled() {
+            annotations.push(Annotation::Clock);
+        }
+
+        if self.data.options.show_evals() {
+            annotations.push(Annotation::Eval);
+        }
+
+        annotations
+    }
+
+    /// Number of columns occupied by a single half-move, including the annotations.
+    fn move_width(&self) -> u32 {
+        1 + self.annotations().len() as u32
+    }
+
+    /// Number of columns occupied by one of the two halves of the moves table.
+    fn half_width(&self) -> u32 {
+        1 + 2 * self.move_width()
+    }
+
+    /// Number of columns occupied by the whole game.
+    fn game_width(&self) -> u32 {
+        2 * self.half_width()
+    }
+
+    fn format_move(&self, m: &Move) -> String {
+        let options = &self.data.options;
+        let algebraic = if options.long_algebraic {
+            &m.lan
+        } else {
+            &m.san
+        };
+
+        options.notation.localize(algebraic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+    use crate::xlsx::Clocks;
+
+    fn merged_ranges(data: Data) -> Vec<String> {
+        let game = || pgn::parse("1. e4 e5 2. Nf3 Nc6 *", Language::En).unwrap();
+        let report = Report::new(data, game(), game(), &Config::default());
+        let book = report.generate_spreadsheet().unwrap();
+
+        book.get_sheet(&0)
+            .unwrap()
+            .get_merge_cells()
+            .iter()
+            .map(|range| range.get_range())
+            .collect()
+    }
+
+    #[test]
+    fn header_spans_both_games() {
+        let ranges = merged_ranges(Data::default());
+
+        for range in ["B1:M1", "C3:F3", "H3:J3", "K4:M4", "B7:G7", "H7:M7"] {
+            assert!(
+                ranges.contains(&String::from(range)),
+                "{range} is not merged"
+            );
+        }
+    }
+
+    #[test]
+    fn header_is_widened_with_annotations() {
+        let mut data = Data::default();
+        data.options.clocks = Clocks::Remaining;
+        data.options.evals = true;
+
+        // Each game spans 14 columns: two halves of a move number and two moves of 3 columns.
+        let ranges = merged_ranges(data);
+
+        for range in [
+            "B1:AC1", "C3:L3", "N3:U3", "V3:AC3", "V4:AC4", "B7:O7", "P7:AC7",
+        ] {
+            assert!(
+                ranges.contains(&String::from(range)),
+                "{range} is not merged: {ranges:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn writes_games_starting_with_black_move() {
+        let pgn = "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]\n\n\
+            30... Kd7 { [%eval 0.0] } 31. e4 { [%eval -5.0] } *";
+        let game = || pgn::parse(pgn, Language::En).unwrap();
+        let mut data = Data::default();
+        data.options.evals = true;
+
+        let report = Report::new(data, game(), game(), &Config::default());
+        let book = report.generate_spreadsheet().unwrap();
+        let sheet = book.get_sheet(&0).unwrap();
+        let value = |cell: &str| sheet.get_value(cell);
+
+        // The first White cell is left empty and the rows are numbered from the first move.
+        assert_eq!(value("B14"), "30");
+        assert_eq!(value("C14"), MOVE_PLACEHOLDER);
+        assert_eq!(value("E14"), "Kd7");
+        assert_eq!(value("F14"), "+0.0");
+        assert_eq!(value("B15"), "31");
+        assert!(value("C15").starts_with("e4"));
+
+        let values: Vec<_> = sheet
+            .get_cell_collection()
+            .iter()
+            .map(|c| c.get_value())
+            .collect();
+        assert!(
+            values.iter().any(|v| v.starts_with("31. e4??")),
+            "{values:?}"
+        );
+    }
 }
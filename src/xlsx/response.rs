@@ -1,54 +1,150 @@
 use std::io::Cursor;
 
+use anyhow::anyhow;
 use axum::{
     http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use umya_spreadsheet::Spreadsheet;
+use utoipa::{
+    ToResponse,
+    openapi::{
+        ContentBuilder, KnownFormat, ObjectBuilder, RefOr, ResponseBuilder, SchemaFormat, Type,
+        header::HeaderBuilder,
+    },
+};
+
+use crate::xlsx::filename::{sanitize, transliterate};
+
+const CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
 
 pub struct XlsxResponse {
     filename: String,
-    spreadsheet: Spreadsheet,
+    bytes: Vec<u8>,
 }
 
 impl XlsxResponse {
-    pub fn new(filename: impl Into<String>, spreadsheet: Spreadsheet) -> Self {
+    pub fn new(filename: impl Into<String>, bytes: Vec<u8>) -> Self {
         Self {
             filename: filename.into(),
-            spreadsheet,
+            bytes,
         }
     }
 }
 
+/// Writes the spreadsheet as XLSX file contents.
+pub fn spreadsheet_to_bytes(spreadsheet: &Spreadsheet) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+
+    umya_spreadsheet::writer::xlsx::write_writer(spreadsheet, &mut buffer)
+        .map_err(|e| anyhow!("Failed to generate spreadsheet: {e}"))?;
+
+    Ok(buffer.into_inner())
+}
+
+/// Formats `Content-Disposition` header with the filename encoded according to RFC 6266 and
+/// RFC 5987. Clients without `filename*` support get the transliterated ASCII filename.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = transliterate(&sanitize(filename))
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let encoded: String = filename
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => String::from(byte as char),
+            byte => format!("%{byte:02X}"),
+        })
+        .collect();
+
+    format!(r#"attachment; filename="{fallback}"; filename*=UTF-8''{encoded}"#)
+}
+
 impl IntoResponse for XlsxResponse {
     fn into_response(self) -> Response {
-        let mut buffer = Cursor::new(Vec::new());
+        (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, CONTENT_TYPE),
+                (
+                    header::CONTENT_DISPOSITION,
+                    &content_disposition(&self.filename),
+                ),
+            ],
+            self.bytes,
+        )
+            .into_response()
+    }
+}
 
-        match umya_spreadsheet::writer::xlsx::write_writer(&self.spreadsheet, &mut buffer) {
-            Ok(_) => {
-                let bytes = buffer.into_inner();
+impl<'s> ToResponse<'s> for XlsxResponse {
+    fn response() -> (&'s str, RefOr<utoipa::openapi::Response>) {
+        let file = ObjectBuilder::new()
+            .schema_type(Type::String)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)));
+        let disposition = HeaderBuilder::new()
+            .schema(ObjectBuilder::new().schema_type(Type::String))
+            .description(Some(
+                "Attachment with the filename, the UTF-8 one in `filename*`",
+            ));
 
-                (
-                    StatusCode::OK,
-                    [
-                        (
-                            header::CONTENT_TYPE,
-                            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-                        ),
-                        (
-                            header::CONTENT_DISPOSITION,
-                            &format!(r#"attachment; filename="{}""#, &self.filename),
-                        ),
-                    ],
-                    bytes,
-                )
-                    .into_response()
-            }
-            Err(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to generate spreadsheet: {e}"),
+        let response = ResponseBuilder::new()
+            .description("XLSX report")
+            .header("Content-Disposition", disposition.build())
+            .content(
+                CONTENT_TYPE,
+                ContentBuilder::new().schema(Some(file)).build(),
             )
-                .into_response(),
-        }
+            .build();
+
+        ("XlsxReport", response.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_filename() {
+        assert_eq!(
+            content_disposition("Otchet_123456_2025-03-14.xlsx"),
+            "attachment; filename=\"Otchet_123456_2025-03-14.xlsx\"; \
+             filename*=UTF-8''Otchet_123456_2025-03-14.xlsx"
+        );
+        assert_eq!(
+            content_disposition("Отчёт Петров.xlsx"),
+            "attachment; filename=\"Otchet Petrov.xlsx\"; \
+             filename*=UTF-8''%D0%9E%D1%82%D1%87%D1%91%D1%82%20%D0%9F%D0%B5%D1%82%D1%80%D0%BE%D0%B2.xlsx"
+        );
+    }
+
+    #[test]
+    fn replaces_characters_in_fallback_filename() {
+        assert_eq!(
+            content_disposition("a\"b;ü.xlsx"),
+            "attachment; filename=\"a_b;_.xlsx\"; filename*=UTF-8''a%22b%3B%C3%BC.xlsx"
+        );
     }
 }
@@ -1,8 +1,15 @@
+use serde::{Deserialize, Serialize};
 use umya_spreadsheet::{
     Alignment, Border, Color, Font, HorizontalAlignmentValues, Style, VerticalAlignmentValues,
 };
 
+use crate::eval::Judgement;
+
+/// Styles of the report cells.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
 pub struct Styles {
+    /// Color of the links and the move numbers in ARGB format, e.g. `ff2e75b6`.
     accent_color: String,
     font_name: String,
 }
@@ -79,6 +86,15 @@ impl Styles {
         style
     }
 
+    pub fn game_opening(&self) -> Style {
+        let font = self.font_normal().set_italic(true).to_owned();
+
+        Style::default()
+            .set_alignment(self.align_center())
+            .set_font(font)
+            .to_owned()
+    }
+
     pub fn game_move(&self) -> Style {
         let mut style = Style::default().to_owned();
         let borders = style.get_borders_mut();
@@ -92,6 +108,31 @@ impl Styles {
         style
     }
 
+    pub fn game_move_judgement(&self, judgement: Judgement) -> Style {
+        let color = match judgement {
+            Judgement::Inaccuracy => "ffffeb9c",
+            Judgement::Mistake => "fffcc88c",
+            Judgement::Blunder => "ffffa0a0",
+        };
+
+        self.game_move().set_background_color(color).to_owned()
+    }
+
+    pub fn game_annotation(&self) -> Style {
+        let mut style = self.game_move();
+
+        let font = self
+            .font_normal()
+            .set_size(8_f64)
+            .set_color(self.accent_color())
+            .to_owned();
+
+        style
+            .set_alignment(self.align_center())
+            .set_font(font)
+            .to_owned()
+    }
+
     pub fn game_move_number(&self) -> Style {
         let mut style = Style::default().to_owned();
         let borders = style.get_borders_mut();
@@ -123,6 +164,26 @@ impl Styles {
         style.set_alignment(self.align_center()).to_owned()
     }
 
+    pub fn game_summary_label(&self) -> Style {
+        Style::default()
+            .set_alignment(self.align_left())
+            .set_font(self.font_normal())
+            .to_owned()
+    }
+
+    pub fn game_link(&self) -> Style {
+        let font = self
+            .font_normal()
+            .set_underline("single")
+            .set_color(self.accent_color())
+            .to_owned();
+
+        Style::default()
+            .set_alignment(self.align_left())
+            .set_font(font)
+            .to_owned()
+    }
+
     pub fn game_result(&self) -> Style {
         Style::default()
             .set_alignment(self.align_center())
@@ -140,6 +201,15 @@ impl Styles {
         center
     }
 
+    fn align_left(&self) -> Alignment {
+        let mut left = Alignment::default();
+
+        left.set_vertical(VerticalAlignmentValues::Center);
+        left.set_horizontal(HorizontalAlignmentValues::Left);
+
+        left
+    }
+
     fn accent_color(&self) -> Color {
         Color::default().set_argb(&self.accent_color).to_owned()
     }
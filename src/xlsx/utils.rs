@@ -1,3 +1,5 @@
+use umya_spreadsheet::helper::coordinate::coordinate_from_index;
+
 pub fn calc_row_count(moves_white: usize, moves_black: usize) -> u32 {
     let x = (moves_white / 2) as u32;
     let y = (moves_black / 2) as u32;
@@ -8,3 +10,12 @@ pub fn calc_row_count(moves_white: usize, moves_black: usize) -> u32 {
 
     moves + remaining
 }
+
+/// Formats range of cells between two `(column, row)` coordinates, e.g. `B7:G7`.
+pub fn range(start: (u32, u32), end: (u32, u32)) -> String {
+    format!(
+        "{}:{}",
+        coordinate_from_index(&start.0, &start.1),
+        coordinate_from_index(&end.0, &end.1),
+    )
+}
@@ -6,10 +6,33 @@ const inputSubjectTeacher = document.getElementById("input-subject-teacher")
 const inputGameOpponent = document.getElementById("input-game-opponent")
 const inputGameWhite = document.getElementById("input-game-white")
 const inputGameBlack = document.getElementById("input-game-black")
+const inputGameWhitePgn = document.getElementById("input-game-white-pgn")
+const inputGameBlackPgn = document.getElementById("input-game-black-pgn")
+const inputOptionsLanguage = document.getElementById("input-options-language")
+const inputOptionsNotation = document.getElementById("input-options-notation")
+const inputOptionsLongAlgebraic = document.getElementById("input-options-long-algebraic")
+const inputOptionsClocks = document.getElementById("input-options-clocks")
+const inputOptionsEvals = document.getElementById("input-options-evals")
+const inputOptionsAnalysis = document.getElementById("input-options-analysis")
+const inputsOptionsMetadata = document.getElementsByName("options.metadata")
 
 const responseField = document.getElementById("response")
 const responseText = document.getElementById("response-text")
 
+// Inputs by the path of the field in the request body, used to highlight invalid fields.
+const inputsByField = {
+    "student.name": inputStudentName,
+    "student.group": inputStudentGroup,
+    "student.id": inputStudentId,
+    "subject.tournament": inputSubjectTournament,
+    "subject.teacher": inputSubjectTeacher,
+    "game.opponent": inputGameOpponent,
+    "game.white_url": inputGameWhite,
+    "game.black_url": inputGameBlack,
+    "game.white_pgn": inputGameWhitePgn,
+    "game.black_pgn": inputGameBlackPgn,
+}
+
 function handleFormSubmission(e) {
     e.preventDefault();
 
@@ -29,21 +52,34 @@ function handleFormSubmission(e) {
             white_url: inputGameWhite.value,
             black_url: inputGameBlack.value,
         },
+        options: {
+            language: inputOptionsLanguage.value,
+            notation: inputOptionsNotation.value,
+            long_algebraic: inputOptionsLongAlgebraic.checked,
+            clocks: inputOptionsClocks.value,
+            evals: inputOptionsEvals.checked,
+            analysis: inputOptionsAnalysis.checked,
+            metadata: getCheckedMetadata(),
+        },
     }
 
     displayResponse("Подождите...")
+    clearInvalidFields()
+
+    // Uploaded PGN files can only be sent as a multipart form, which has the same fields.
+    const hasUploads = inputGameWhitePgn.files.length > 0 || inputGameBlackPgn.files.length > 0
+    const request = hasUploads
+        ? { body: new FormData(e.target) }
+        : { headers: { "Content-Type": "application/json" }, body: JSON.stringify(requestBody) }
 
     fetch(apiURL, {
         method: "POST",
-        headers: {
-            "Accept": "application/json",
-            "Content-Type": "application/json",
-        },
-        body: JSON.stringify(requestBody),
+        headers: { "Accept": "application/json", ...request.headers },
+        body: request.body,
     })
         .then((response) => {
             if (!response.ok) {
-                return response.text().then((err) => Promise.reject(err))
+                return readError(response).then((err) => Promise.reject(err))
             }
             return response.blob().then((blob) => ({
                 blob: blob,
@@ -54,15 +90,23 @@ function handleFormSubmission(e) {
             downloadBlob(blob, filename)
             displayResponse("Отчет успешно сгенерирован!")
         })
-        .catch(displayResponse);
+        .catch(displayError);
 
     saveCommonData()
 }
 
+function getCheckedMetadata() {
+    return Array.from(inputsOptionsMetadata)
+        .filter((input) => input.checked)
+        .map((input) => input.value)
+}
+
 function getFilenameFromHeader(header) {
     if (!header) return "report.xlsx";
-    const match = header.match(/filename[^;=\n]*=((['"]).*?\2|[^;\n]*)/)
-    return match && match[1] ? decodeURIComponent(match[1].replace(/['"]/g, "")) : "report.xlsx"
+    const encoded = header.match(/filename\*=UTF-8''([^;\n]*)/i)
+    if (encoded) return decodeURIComponent(encoded[1])
+    const match = header.match(/filename="([^"]*)"|filename=([^;\n]*)/)
+    return match ? match[1] || match[2] : "report.xlsx"
 }
 
 function downloadBlob(blob, filename) {
@@ -74,6 +118,40 @@ function downloadBlob(blob, filename) {
     URL.revokeObjectURL(url)
 }
 
+// Errors of the API are JSON, but the ones of the server itself, e.g. `408 Request Timeout`, and
+// of the proxies in front of it may be plain text or HTML.
+function readError(response) {
+    const contentType = response.headers.get("Content-Type") || ""
+    const status = { message: `${response.status} ${response.statusText}`.trim() }
+
+    if (contentType.includes("application/json")) {
+        return response.json().catch(() => status)
+    }
+
+    return response.text()
+        .then((text) => contentType.includes("text/plain") && text.trim() ? { message: text.trim() } : status)
+        .catch(() => status)
+}
+
+function displayError(error) {
+    if (typeof error !== "object" || !error.message) {
+        displayResponse(String(error))
+        return
+    }
+
+    const fieldErrors = error.errors || []
+    fieldErrors.forEach((fieldError) => inputsByField[fieldError.field]?.classList.add("invalid"))
+
+    const messages = fieldErrors.length > 1
+        ? [error.message, ...fieldErrors.map((fieldError) => fieldError.message)]
+        : [error.message]
+    displayResponse(messages.join("\n"))
+}
+
+function clearInvalidFields() {
+    Object.values(inputsByField).forEach((input) => input.classList.remove("invalid"))
+}
+
 function displayResponse(message) {
     responseField.hidden = false;
     responseText.innerText = message
@@ -85,6 +163,13 @@ function saveCommonData() {
     localStorage.setItem("student.id", inputStudentId.value)
     localStorage.setItem("subject.tournament", inputSubjectTournament.value)
     localStorage.setItem("subject.teacher", inputSubjectTeacher.value)
+    localStorage.setItem("options.language", inputOptionsLanguage.value)
+    localStorage.setItem("options.notation", inputOptionsNotation.value)
+    localStorage.setItem("options.long_algebraic", inputOptionsLongAlgebraic.checked)
+    localStorage.setItem("options.clocks", inputOptionsClocks.value)
+    localStorage.setItem("options.evals", inputOptionsEvals.checked)
+    localStorage.setItem("options.analysis", inputOptionsAnalysis.checked)
+    localStorage.setItem("options.metadata", JSON.stringify(getCheckedMetadata()))
 }
 
 function loadCommonData() {
@@ -93,6 +178,15 @@ function loadCommonData() {
     inputStudentId.value = localStorage.getItem("student.id") || ""
     inputSubjectTournament.value = localStorage.getItem("subject.tournament") || ""
     inputSubjectTeacher.value = localStorage.getItem("subject.teacher") || ""
+    inputOptionsLanguage.value = localStorage.getItem("options.language") || "ru"
+    inputOptionsNotation.value = localStorage.getItem("options.notation") || "latin"
+    inputOptionsLongAlgebraic.checked = localStorage.getItem("options.long_algebraic") === "true"
+    inputOptionsClocks.value = localStorage.getItem("options.clocks") || "off"
+    inputOptionsEvals.checked = localStorage.getItem("options.evals") === "true"
+    inputOptionsAnalysis.checked = localStorage.getItem("options.analysis") === "true"
+
+    const metadata = JSON.parse(localStorage.getItem("options.metadata") || "[]")
+    inputsOptionsMetadata.forEach((input) => input.checked = metadata.includes(input.value))
 }
 
 document
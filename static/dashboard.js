@@ -0,0 +1,117 @@
+const inputFilterGroup = document.getElementById("input-filter-group")
+const inputFilterTournament = document.getElementById("input-filter-tournament")
+
+const submissionsTable = document.getElementById("submissions")
+const submissionsBody = document.getElementById("submissions-body")
+const moreSubmissionsButton = document.getElementById("more-submissions")
+
+const responseField = document.getElementById("response")
+const responseText = document.getElementById("response-text")
+
+const flagLabels = {
+    duplicate_game: "Повторная партия",
+    wrong_color: "Неверный цвет",
+    short_game: "Короткая партия",
+}
+
+// ID of the last report shown, to load the next page of the same filter.
+let nextPage = null
+
+function handleFilterSubmission(e) {
+    e.preventDefault();
+
+    submissionsBody.replaceChildren()
+    loadSubmissions(null)
+
+    localStorage.setItem("filter.group", inputFilterGroup.value)
+    localStorage.setItem("filter.tournament", inputFilterTournament.value)
+}
+
+function loadSubmissions(before) {
+    const apiURL = new URL("/api/v1/submissions", window.location.origin)
+    apiURL.searchParams.set("group", inputFilterGroup.value)
+    apiURL.searchParams.set("tournament", inputFilterTournament.value)
+    if (before !== null) {
+        apiURL.searchParams.set("before", before)
+    }
+
+    displayResponse("Подождите...")
+
+    fetch(apiURL, { headers: { "Accept": "application/json" } })
+        .then((response) => {
+            if (response.status === 401) {
+                window.location.assign("/login?next=/dashboard")
+                return Promise.reject("Необходимо войти в систему")
+            }
+            if (!response.ok) {
+                return response.json().then((err) => Promise.reject(err.message))
+            }
+            return response.json()
+        })
+        .then(displaySubmissions)
+        .catch(displayResponse);
+}
+
+function displaySubmissions(page) {
+    submissionsBody.append(...page.submissions.map(submissionRow))
+
+    nextPage = page.next ?? null
+    moreSubmissionsButton.hidden = nextPage === null
+
+    const empty = submissionsBody.children.length === 0
+    submissionsTable.hidden = empty
+
+    if (empty) {
+        displayResponse("Отчеты не найдены")
+    } else {
+        responseField.hidden = true
+    }
+}
+
+function submissionRow(submission) {
+    const row = document.createElement("tr")
+
+    const link = document.createElement("a")
+    link.href = submission.download_url
+    link.innerText = "Скачать"
+
+    row.append(
+        cell(`${submission.student_name} (${submission.student_id})`),
+        cell(submission.group),
+        cell(submission.tournament),
+        cell(gameSummary(submission.white)),
+        cell(gameSummary(submission.black)),
+        cell(submission.flags.map((flag) => flagLabels[flag] || flag).join(", ")),
+        cell(link),
+    )
+
+    if (submission.flags.length > 0) {
+        row.classList.add("flagged")
+    }
+
+    return row
+}
+
+function gameSummary(game) {
+    return `${game.points ?? "—"} (${game.moves} ходов)`
+}
+
+function cell(content) {
+    const td = document.createElement("td")
+    td.append(content)
+    return td
+}
+
+function displayResponse(message) {
+    responseField.hidden = false;
+    responseText.innerText = message
+}
+
+inputFilterGroup.value = localStorage.getItem("filter.group") || ""
+inputFilterTournament.value = localStorage.getItem("filter.tournament") || ""
+
+document
+    .getElementById("filter-form")
+    .addEventListener("submit", handleFilterSubmission)
+
+moreSubmissionsButton.addEventListener("click", () => loadSubmissions(nextPage))
@@ -0,0 +1,60 @@
+const inputLogin = document.getElementById("input-login")
+const inputPassword = document.getElementById("input-password")
+
+const responseField = document.getElementById("response")
+const responseText = document.getElementById("response-text")
+
+function handleLoginSubmission(e) {
+    e.preventDefault();
+
+    const apiURL = new URL("/api/v1/login", window.location.origin)
+
+    fetch(apiURL, {
+        method: "POST",
+        headers: {
+            "Accept": "application/json",
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({
+            login: inputLogin.value,
+            password: inputPassword.value,
+        }),
+    })
+        .then((response) => {
+            if (!response.ok) {
+                return response.json().then((err) => Promise.reject(err.message))
+            }
+            return response.json()
+        })
+        .then(() => window.location.assign(getRedirectPath()))
+        .catch(displayResponse);
+}
+
+function getRedirectPath() {
+    const next = new URLSearchParams(window.location.search).get("next")
+    if (!next) {
+        return "/dashboard"
+    }
+
+    // Only redirect within the site. Browsers resolve paths like `/\evil.com` to other hosts,
+    // so the path is resolved the same way before comparing the origins.
+    try {
+        const url = new URL(next, window.location.origin)
+        if (url.origin === window.location.origin) {
+            return url.pathname + url.search + url.hash
+        }
+    } catch {
+        // Invalid URLs fall back to the dashboard.
+    }
+
+    return "/dashboard"
+}
+
+function displayResponse(message) {
+    responseField.hidden = false;
+    responseText.innerText = message
+}
+
+document
+    .getElementById("login-form")
+    .addEventListener("submit", handleLoginSubmission)
@@ -0,0 +1,21 @@
+#!/bin/sh
+# Minimal UCI engine for tests. Evaluates every position as +0.50 for the side to move.
+
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Fake Engine"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        go*)
+            echo "info depth 1 score cp 50 pv e2e4"
+            echo "bestmove e2e4"
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done
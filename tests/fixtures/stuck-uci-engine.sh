@@ -0,0 +1,19 @@
+#!/bin/sh
+# UCI engine for tests that analyses like the fake engine but ignores `quit` and keeps running.
+
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        go*)
+            echo "info depth 1 score cp 50 pv e2e4"
+            echo "bestmove e2e4"
+            ;;
+    esac
+done
+
+sleep 60